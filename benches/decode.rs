@@ -0,0 +1,41 @@
+//! Compares the strict and unchecked decode paths.
+//!
+//! Run with `cargo bench --bench decode`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use ws_frame::Frame;
+
+const ITERATIONS: u32 = 10_000_000;
+
+fn bench(name: &str, buf: &[u8], decode: fn(&mut Frame, &[u8]) -> bool) {
+    let mut frame = Frame::empty();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert!(decode(&mut frame, black_box(buf)));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<28} {:>8.2} ns/frame",
+        name,
+        elapsed.as_nanos() as f64 / f64::from(ITERATIONS)
+    );
+}
+
+fn main() {
+    let frames: [(&str, &[u8]); 3] = [
+        ("short", &[0x81, 0x05]),
+        ("extended16 masked", &[0x82, 0xFE, 0x01, 0x00, 1, 2, 3, 4]),
+        ("extended64", &[0x82, 0x7F, 0, 0, 0, 0, 0, 1, 0, 0]),
+    ];
+
+    for (name, buf) in &frames {
+        bench(&format!("strict/{}", name), buf, |f, buf| {
+            f.decode_strict(buf).is_complete()
+        });
+        bench(&format!("unchecked/{}", name), buf, |f, buf| {
+            f.decode_unchecked(buf).is_complete()
+        });
+    }
+}
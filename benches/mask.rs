@@ -0,0 +1,29 @@
+//! Measures masking throughput.
+//!
+//! Run with `cargo bench --bench mask`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use ws_frame::apply_mask;
+
+fn main() {
+    let key = [0x12, 0x34, 0x56, 0x78];
+    for &len in &[64, 4 * 1024, 1024 * 1024] {
+        let mut payload = vec![0xAA; len + 1];
+        let iterations = (256 * 1024 * 1024 / len) as u32;
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            // Offset by a byte so the unaligned ends are exercised too.
+            apply_mask(key, black_box(&mut payload[1..]));
+        }
+        let elapsed = start.elapsed();
+        let bytes = len as f64 * f64::from(iterations);
+        println!(
+            "apply_mask/{:<8} {:>8.2} GiB/s",
+            len,
+            bytes / elapsed.as_secs_f64() / (1u64 << 30) as f64
+        );
+    }
+}
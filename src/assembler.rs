@@ -0,0 +1,176 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{FrameError, Head, Message, Opcode, OwnedMessage, Utf8Validator};
+
+/// Puts fragmented messages back together from a sequence of frames.
+///
+/// Frames are [`push`](MessageAssembler::push)ed in the order they were
+/// received, with their payloads unmasked. Control frames may come between
+/// the fragments of a message and are yielded right away, while a data
+/// message is yielded once its final fragment arrives. Text is checked to be
+/// UTF-8 as each fragment comes in.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Head, MessageAssembler, Opcode, OwnedMessage};
+///
+/// let head = |op, finished| Head { op, finished, rsv: [false; 3] };
+/// let mut assembler = MessageAssembler::new();
+///
+/// assert_eq!(Ok(None), assembler.push(&head(Opcode::Text, false), b"hel"));
+/// assert_eq!(
+///     Ok(Some(OwnedMessage::Ping(Vec::new()))),
+///     assembler.push(&head(Opcode::Ping, true), b"")
+/// );
+/// assert_eq!(
+///     Ok(Some(OwnedMessage::Text("hello".into()))),
+///     assembler.push(&head(Opcode::Continue, true), b"lo")
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MessageAssembler {
+    op: Option<Opcode>,
+    buf: Vec<u8>,
+    utf8: Utf8Validator,
+}
+
+impl MessageAssembler {
+    /// Creates an assembler that isn't in the middle of a message.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a frame with its unmasked payload, returning a message if one
+    /// is complete.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::InvalidOpcode`] for a continuation outside of a
+    /// message, a new Text or Binary frame in the middle of one, or a
+    /// reserved opcode, and [`FrameError::InvalidUtf8`] if a text message
+    /// isn't UTF-8. A control frame fails as in [`Message::from_frame`].
+    /// Any message in progress is dropped on an error.
+    pub fn push(
+        &mut self,
+        head: &Head,
+        payload: &[u8],
+    ) -> Result<Option<OwnedMessage>, FrameError> {
+        let result = self.assemble(head, payload);
+        if result.is_err() {
+            self.reset();
+        }
+        result
+    }
+
+    /// Returns true if a fragmented message is in progress.
+    pub fn is_assembling(&self) -> bool {
+        self.op.is_some()
+    }
+
+    /// Drops any message in progress.
+    pub fn reset(&mut self) {
+        self.op = None;
+        self.buf.clear();
+        self.utf8.reset();
+    }
+
+    fn assemble(
+        &mut self,
+        head: &Head,
+        payload: &[u8],
+    ) -> Result<Option<OwnedMessage>, FrameError> {
+        if head.op.is_control() {
+            return Message::from_frame(head, payload).map(|m| Some(m.into_owned()));
+        }
+
+        let op = match (head.op, self.op) {
+            (Opcode::Continue, Some(op)) => op,
+            (op @ Opcode::Text, None) | (op @ Opcode::Binary, None) => op,
+            _ => return Err(FrameError::InvalidOpcode),
+        };
+        if op == Opcode::Text {
+            self.utf8.feed(payload)?;
+        }
+        self.buf.extend_from_slice(payload);
+
+        if !head.finished {
+            self.op = Some(op);
+            return Ok(None);
+        }
+
+        let buf = core::mem::take(&mut self.buf);
+        self.op = None;
+        if op == Opcode::Text {
+            self.utf8.finish()?;
+            self.utf8.reset();
+            let text = String::from_utf8(buf).map_err(|_| FrameError::InvalidUtf8)?;
+            Ok(Some(OwnedMessage::Text(text)))
+        } else {
+            Ok(Some(OwnedMessage::Binary(buf)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(op: Opcode, finished: bool) -> Head {
+        Head {
+            op,
+            finished,
+            rsv: [false; 3],
+        }
+    }
+
+    #[test]
+    fn enforces_fragment_order() {
+        let mut assembler = MessageAssembler::new();
+        assert_eq!(
+            Err(FrameError::InvalidOpcode),
+            assembler.push(&head(Opcode::Continue, true), b"a")
+        );
+
+        assert_eq!(Ok(None), assembler.push(&head(Opcode::Binary, false), b"a"));
+        assert_eq!(
+            Err(FrameError::InvalidOpcode),
+            assembler.push(&head(Opcode::Text, true), b"b")
+        );
+        assert!(!assembler.is_assembling());
+
+        assert_eq!(Ok(None), assembler.push(&head(Opcode::Binary, false), b"a"));
+        assert_eq!(
+            Ok(None),
+            assembler.push(&head(Opcode::Continue, false), b"")
+        );
+        assert_eq!(
+            Ok(Some(OwnedMessage::Binary(b"ab".to_vec()))),
+            assembler.push(&head(Opcode::Continue, true), b"b")
+        );
+    }
+
+    #[test]
+    fn checks_text_across_fragments() {
+        let mut assembler = MessageAssembler::new();
+        assert_eq!(
+            Ok(None),
+            assembler.push(&head(Opcode::Text, false), &[0xC3])
+        );
+        assert_eq!(
+            Ok(Some(OwnedMessage::Text("é".into()))),
+            assembler.push(&head(Opcode::Continue, true), &[0xA9])
+        );
+
+        assert_eq!(
+            Ok(None),
+            assembler.push(&head(Opcode::Text, false), &[0xC3])
+        );
+        assert_eq!(
+            Err(FrameError::InvalidUtf8),
+            assembler.push(&head(Opcode::Continue, true), &[])
+        );
+        assert_eq!(
+            Err(FrameError::InvalidUtf8),
+            assembler.push(&head(Opcode::Text, false), &[0xFF])
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use axum::extract::ws::{CloseFrame, Message as AxumMessage};
+
+use crate::{CloseCode, OwnedMessage};
+
+impl From<OwnedMessage> for AxumMessage {
+    fn from(message: OwnedMessage) -> AxumMessage {
+        match message {
+            OwnedMessage::Text(text) => AxumMessage::Text(text.into()),
+            OwnedMessage::Binary(payload) => AxumMessage::Binary(payload.into()),
+            OwnedMessage::Ping(payload) => AxumMessage::Ping(payload.into()),
+            OwnedMessage::Pong(payload) => AxumMessage::Pong(payload.into()),
+            OwnedMessage::Close(close) => {
+                AxumMessage::Close(close.map(|(code, reason)| CloseFrame {
+                    code: code.into(),
+                    reason: reason.into(),
+                }))
+            }
+        }
+    }
+}
+
+impl From<AxumMessage> for OwnedMessage {
+    fn from(message: AxumMessage) -> OwnedMessage {
+        match message {
+            AxumMessage::Text(text) => OwnedMessage::Text(text.as_str().into()),
+            AxumMessage::Binary(payload) => OwnedMessage::Binary(payload.into()),
+            AxumMessage::Ping(payload) => OwnedMessage::Ping(payload.into()),
+            AxumMessage::Pong(payload) => OwnedMessage::Pong(payload.into()),
+            AxumMessage::Close(close) => OwnedMessage::Close(
+                close.map(|frame| (CloseCode::from(frame.code), frame.reason.as_str().into())),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_both_ways() {
+        let messages = [
+            OwnedMessage::Text("hi".into()),
+            OwnedMessage::Binary(vec![1, 2]),
+            OwnedMessage::Ping(vec![3]),
+            OwnedMessage::Pong(Vec::new()),
+            OwnedMessage::Close(None),
+            OwnedMessage::Close(Some((CloseCode::GoingAway, "bye".into()))),
+        ];
+        for message in messages {
+            let axum = AxumMessage::from(message.clone());
+            assert_eq!(message, OwnedMessage::from(axum));
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use std::io::{self, IoSlice, Read, Write};
+
+use crate::encode::encode_to_vec;
+use crate::{
+    head_len, ControlChecks, Frame, FrameError, Head, Opcode, Sequenced, Sequencer, Status,
+    ValidationPolicy,
+};
+
+/// Reads exactly one frame header from `reader`.
+///
+/// The first two bytes are read to learn the size of the rest of the header,
+/// and then exactly that much is read. No payload bytes are ever read, so the
+/// reader is left positioned at the start of the payload.
+///
+/// # Errors
+/// Returns an error of kind `InvalidData` if the header is rejected, and any
+/// error returned by `reader`.
+///
+/// # Example
+/// ```
+/// use ws_frame::read_head;
+///
+/// let mut reader = &[0x82, 0x7E, 0x01, 0x00, 0xAA][..];
+/// let frame = read_head(&mut reader).unwrap();
+///
+/// assert_eq!(Some(256), frame.payload_len);
+/// assert_eq!(&[0xAA], reader);
+/// ```
+pub fn read_head<R: Read + ?Sized>(reader: &mut R) -> io::Result<Frame> {
+    read_head_with(reader, &mut ControlChecks)?.map_err(invalid_data)
+}
+
+/// Reads one frame header as [`read_head`] does, checking it against
+/// `policy`. A rejected header is the inner error.
+pub(crate) fn read_head_with<R, P>(
+    reader: &mut R,
+    policy: &mut P,
+) -> io::Result<Result<Frame, FrameError>>
+where
+    R: Read + ?Sized,
+    P: ValidationPolicy + ?Sized,
+{
+    let mut buf = [0; 14];
+    reader.read_exact(&mut buf[..2])?;
+
+    let len = head_len(buf[1]);
+    paranoid_assert!(len >= 2 && len <= buf.len());
+    reader.read_exact(&mut buf[2..len])?;
+
+    let mut frame = Frame::empty();
+    match frame.decode_with(&buf[..len], policy) {
+        Status::Complete(_) => Ok(Ok(frame)),
+        Status::Partial(_) => unreachable!("read a complete header"),
+        Status::Error(e) => Ok(Err(e)),
+    }
+}
+
+/// Packs small frames into as few writes as possible.
+///
+/// Encoded frames are gathered in a buffer, which is written to the
+/// underlying writer in one go once it holds at least `threshold` bytes, or
+/// when [`flush`](CoalescingWriter::flush) is called.
+///
+/// Writes can also be held back with [`cork`](CoalescingWriter::cork), so a
+/// burst of frames is sent as a unit when the writer is uncorked.
+///
+/// Buffered frames are lost if the writer is dropped without being flushed.
+///
+/// # Example
+/// ```
+/// use ws_frame::{CoalescingWriter, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Text, finished: true, rsv: [false; 3] };
+/// let mut writer = CoalescingWriter::new(Vec::new(), 1024);
+///
+/// writer.write_frame(&head, b"a", None).unwrap();
+/// writer.write_frame(&head, b"b", None).unwrap();
+/// assert!(writer.get_ref().is_empty());
+///
+/// writer.flush().unwrap();
+/// assert_eq!(&[0x81, 0x01, b'a', 0x81, 0x01, b'b'], &writer.get_ref()[..]);
+/// ```
+#[derive(Debug)]
+pub struct CoalescingWriter<W> {
+    writer: W,
+    buf: Vec<u8>,
+    threshold: usize,
+    corked: bool,
+    cork_limit: Option<usize>,
+    seq: Sequencer,
+}
+
+impl<W: Write> CoalescingWriter<W> {
+    /// Creates a writer that writes once `threshold` bytes are buffered.
+    pub fn new(writer: W, threshold: usize) -> Self {
+        Self {
+            writer,
+            buf: Vec::new(),
+            threshold,
+            corked: false,
+            cork_limit: None,
+            seq: Sequencer::new(),
+        }
+    }
+
+    /// Holds back writes until [`uncork`](CoalescingWriter::uncork) is
+    /// called, regardless of the threshold.
+    pub fn cork(&mut self) {
+        self.corked = true;
+    }
+
+    /// Stops holding back writes, and writes out and flushes every buffered
+    /// frame.
+    pub fn uncork(&mut self) -> io::Result<()> {
+        self.corked = false;
+        self.flush()
+    }
+
+    /// Returns whether writes are being held back.
+    pub fn is_corked(&self) -> bool {
+        self.corked
+    }
+
+    /// Sets the amount of buffered bytes at which a corked writer writes out
+    /// anyway, or `None` to hold everything until uncorked.
+    pub fn set_cork_limit(&mut self, limit: Option<usize>) {
+        self.cork_limit = limit;
+    }
+
+    /// Encodes a frame, writing out the buffer if it reached the threshold,
+    /// or the cork limit while corked.
+    ///
+    /// The payload is masked with `mask` if one is given.
+    pub fn write_frame(
+        &mut self,
+        head: &Head,
+        payload: &[u8],
+        mask: Option<[u8; 4]>,
+    ) -> io::Result<()> {
+        self.write_frame_sequenced(head, payload, mask)
+            .map(drop)
+            .map_err(|e| e.value)
+    }
+
+    /// Like [`write_frame`](CoalescingWriter::write_frame), but returns the
+    /// sequence number given to the frame, counting from 0 in the order
+    /// frames are written.
+    ///
+    /// An error is tagged with the number of the frame that wasn't encoded,
+    /// or whose write failed.
+    pub fn write_frame_sequenced(
+        &mut self,
+        head: &Head,
+        payload: &[u8],
+        mask: Option<[u8; 4]>,
+    ) -> Result<u64, Sequenced<io::Error>> {
+        let seq = self.seq.peek();
+        let tag = |value| Sequenced { seq, value };
+        encode_to_vec(head, payload, mask, &mut self.buf).map_err(|e| tag(invalid_data(e)))?;
+        self.seq.next_seq();
+        let limit = if self.corked {
+            self.cork_limit
+        } else {
+            Some(self.threshold)
+        };
+        if limit.is_some_and(|limit| self.buf.len() >= limit) {
+            self.write_buf().map_err(tag)?;
+        }
+        paranoid_assert!(self.corked || self.buf.len() <= self.threshold);
+        Ok(seq)
+    }
+
+    /// Returns the amount of bytes waiting to be written, including any left
+    /// over from a failed write.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Writes out every buffered frame and flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_buf()?;
+        self.writer.flush()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes this `CoalescingWriter`, returning the underlying writer.
+    ///
+    /// Frames still in the buffer are discarded.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes out the buffer, keeping whatever wasn't written if that fails
+    /// so the next write or flush picks up where this one left off.
+    fn write_buf(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+            match self.writer.write(&self.buf[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.drain(..written);
+        result
+    }
+}
+
+/// Queues outbound frames and writes them out together, sending Pings and
+/// Pongs first.
+///
+/// Frames are only written on [`flush`](FrameWriter::flush), in as few write
+/// calls as possible. Queued Pings and Pongs are sent ahead of queued data
+/// frames, which RFC 6455 §5.4 allows between the fragments of a message, so
+/// a Pong isn't held up behind a large message. A Close is sent after the
+/// data queued before it, as nothing may follow it (§5.5.1), and any frame
+/// queued after a Close stays behind it.
+///
+/// Queued frames are lost if the writer is dropped without being flushed.
+///
+/// # Example
+/// ```
+/// use ws_frame::{FrameWriter, Head, Opcode};
+///
+/// let text = Head { op: Opcode::Text, finished: false, rsv: [false; 3] };
+/// let pong = Head { op: Opcode::Pong, finished: true, rsv: [false; 3] };
+/// let mut writer = FrameWriter::new(Vec::new());
+///
+/// writer.queue(&text, b"a", None).unwrap();
+/// writer.queue(&pong, &[], None).unwrap();
+/// writer.flush().unwrap();
+/// assert_eq!(&[0x8A, 0x00, 0x01, 0x01, b'a'], &writer.get_ref()[..]);
+/// ```
+#[derive(Debug)]
+pub struct FrameWriter<W> {
+    writer: W,
+    unsent: Vec<u8>,
+    control: Vec<u8>,
+    data: Vec<u8>,
+    close: Vec<u8>,
+    seq: Sequencer,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// Creates a writer with nothing queued.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            unsent: Vec::new(),
+            control: Vec::new(),
+            data: Vec::new(),
+            close: Vec::new(),
+            seq: Sequencer::new(),
+        }
+    }
+
+    /// Encodes a frame and queues it, masking the payload with `mask` if one
+    /// is given.
+    pub fn queue(&mut self, head: &Head, payload: &[u8], mask: Option<[u8; 4]>) -> io::Result<()> {
+        self.queue_sequenced(head, payload, mask)
+            .map(drop)
+            .map_err(|e| e.value)
+    }
+
+    /// Like [`queue`](FrameWriter::queue), but returns the sequence number
+    /// given to the frame, counting from 0 in the order frames are queued.
+    ///
+    /// A Ping or Pong keeps its number when it's sent ahead of data queued
+    /// before it. An error is tagged with the number of the frame that wasn't
+    /// encoded.
+    pub fn queue_sequenced(
+        &mut self,
+        head: &Head,
+        payload: &[u8],
+        mask: Option<[u8; 4]>,
+    ) -> Result<u64, Sequenced<io::Error>> {
+        let seq = self.seq.peek();
+        let buf = if head.op == Opcode::Close || !self.close.is_empty() {
+            &mut self.close
+        } else if head.op.is_control() {
+            &mut self.control
+        } else {
+            &mut self.data
+        };
+        encode_to_vec(head, payload, mask, buf).map_err(|e| Sequenced {
+            seq,
+            value: invalid_data(e),
+        })?;
+        Ok(self.seq.next_seq())
+    }
+
+    /// Returns the amount of bytes queued.
+    pub fn queued(&self) -> usize {
+        self.unsent.len() + self.control.len() + self.data.len() + self.close.len()
+    }
+
+    /// Writes out every queued frame, Pings and Pongs first, and flushes the
+    /// underlying writer.
+    ///
+    /// If writing fails, the bytes that weren't written stay queued in the
+    /// order they were going out, ahead of anything queued later, so calling
+    /// `flush` again picks up where the failed call left off.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let mut bufs = [
+            IoSlice::new(&self.unsent),
+            IoSlice::new(&self.control),
+            IoSlice::new(&self.data),
+            IoSlice::new(&self.close),
+        ];
+        let mut bufs = &mut bufs[..];
+        let result = loop {
+            if bufs.iter().all(|buf| buf.is_empty()) {
+                break Ok(());
+            }
+            match self.writer.write_vectored(bufs) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    written += n;
+                    IoSlice::advance_slices(&mut bufs, n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+
+        let mut unsent = Vec::new();
+        for buf in [
+            &mut self.unsent,
+            &mut self.control,
+            &mut self.data,
+            &mut self.close,
+        ] {
+            let n = written.min(buf.len());
+            written -= n;
+            if result.is_err() {
+                unsent.extend_from_slice(&buf[n..]);
+            }
+            buf.clear();
+        }
+        self.unsent = unsent;
+        result?;
+        self.writer.flush()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes this `FrameWriter`, returning the underlying writer.
+    ///
+    /// Queued frames are discarded.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub(crate) fn invalid_data(e: FrameError) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid frame: {:?}", e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Opcode;
+
+    #[test]
+    fn never_reads_payload() {
+        let mut reader = &[0x81, 0x83, 1, 2, 3, 4, 0x60, 0x60, 0x60][..];
+        let frame = read_head(&mut reader).unwrap();
+        assert_eq!(Some([1, 2, 3, 4]), frame.mask);
+        assert_eq!(3, reader.len());
+
+        let mut reader = &[0x81, 0x7F, 0, 0][..];
+        let err = read_head(&mut reader).unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    }
+
+    #[test]
+    fn control_frames_jump_the_queue() {
+        struct Trickle(Vec<u8>);
+
+        impl Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.extend_from_slice(&buf[..buf.len().min(3)]);
+                Ok(buf.len().min(3))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let head = |op, finished| Head {
+            op,
+            finished,
+            rsv: [false; 3],
+        };
+        let mut writer = FrameWriter::new(Trickle(Vec::new()));
+        writer
+            .queue(&head(Opcode::Binary, false), &[1; 4], None)
+            .unwrap();
+        writer.queue(&head(Opcode::Ping, true), b"p", None).unwrap();
+        writer
+            .queue(&head(Opcode::Continue, true), &[2], None)
+            .unwrap();
+        assert_eq!(12, writer.queued());
+
+        writer.flush().unwrap();
+        assert_eq!(0, writer.queued());
+        assert_eq!(
+            &[0x89, 0x01, b'p', 0x02, 0x04, 1, 1, 1, 1, 0x80, 0x01, 2],
+            &writer.get_ref().0[..]
+        );
+    }
+
+    #[test]
+    fn close_stays_behind_data() {
+        /// Accepts `budget` bytes, then fails until it's refilled.
+        struct Flaky {
+            out: Vec<u8>,
+            budget: usize,
+        }
+
+        impl Write for Flaky {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.budget == 0 {
+                    return Err(io::ErrorKind::BrokenPipe.into());
+                }
+                let n = buf.len().min(self.budget);
+                self.out.extend_from_slice(&buf[..n]);
+                self.budget -= n;
+                Ok(n)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let head = |op| Head {
+            op,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut writer = FrameWriter::new(Flaky {
+            out: Vec::new(),
+            budget: 3,
+        });
+        writer.queue(&head(Opcode::Binary), &[1, 2], None).unwrap();
+        writer.queue(&head(Opcode::Close), &[], None).unwrap();
+        writer.queue(&head(Opcode::Pong), &[], None).unwrap();
+        assert!(writer.flush().is_err());
+        assert_eq!(5, writer.queued());
+
+        writer.writer.budget = usize::MAX;
+        writer.flush().unwrap();
+        assert_eq!(
+            &[0x82, 0x02, 1, 2, 0x88, 0x00, 0x8A, 0x00],
+            &writer.get_ref().out[..]
+        );
+    }
+
+    #[test]
+    fn numbers_written_frames() {
+        let head = |op| Head {
+            op,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut writer = FrameWriter::new(Vec::new());
+        assert_eq!(
+            0,
+            writer
+                .queue_sequenced(&head(Opcode::Text), b"a", None)
+                .unwrap()
+        );
+        assert_eq!(
+            1,
+            writer
+                .queue_sequenced(&head(Opcode::Pong), &[], None)
+                .unwrap()
+        );
+
+        let mut out = [0; 4];
+        let mut writer = CoalescingWriter::new(&mut out[..], 1);
+        writer.write_frame(&head(Opcode::Text), b"a", None).unwrap();
+        let err = writer.write_frame_sequenced(&head(Opcode::Text), b"b", None);
+        assert_eq!(1, err.unwrap_err().seq);
+    }
+
+    #[test]
+    fn writes_once_threshold_is_reached() {
+        let head = Head {
+            op: crate::Opcode::Binary,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut writer = CoalescingWriter::new(Vec::new(), 8);
+
+        writer.write_frame(&head, &[0; 2], None).unwrap();
+        assert_eq!(4, writer.buffered());
+        writer.write_frame(&head, &[0; 2], None).unwrap();
+        assert_eq!(0, writer.buffered());
+        assert_eq!(8, writer.get_ref().len());
+
+        writer.cork();
+        writer.set_cork_limit(Some(16));
+        for _ in 0..3 {
+            writer.write_frame(&head, &[0; 2], None).unwrap();
+        }
+        assert_eq!(12, writer.buffered());
+        writer.write_frame(&head, &[0; 2], None).unwrap();
+        assert_eq!(24, writer.get_ref().len());
+
+        writer.write_frame(&head, &[0; 2], None).unwrap();
+        writer.uncork().unwrap();
+        assert_eq!(28, writer.get_ref().len());
+    }
+
+    #[test]
+    fn keeps_unwritten_bytes() {
+        let head = Head {
+            op: Opcode::Binary,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut out = [0; 5];
+        let mut writer = CoalescingWriter::new(&mut out[..], 0);
+        writer.write_frame(&head, &[1], None).unwrap();
+        assert!(writer.write_frame(&head, &[2], None).is_err());
+        assert_eq!(1, writer.buffered());
+        assert_eq!(&[0x82, 0x01, 1, 0x82, 0x01], &out);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::encode::encode_to_vec;
+use crate::{Frame, FrameError, Head};
+
+/// An encoded frame, with its header and payload in one allocation.
+///
+/// The bytes are ready to write as is, and cloning a `FrameBuf` is enough
+/// to send the same frame to several sinks.
+///
+/// # Example
+/// ```
+/// use ws_frame::{FrameBuf, Head, Opcode};
+///
+/// let head = Head {
+///     op: Opcode::Text,
+///     finished: true,
+///     rsv: [false; 3],
+/// };
+/// let buf = FrameBuf::new(&head, b"hi", None).unwrap();
+/// assert_eq!(&[0x81, 0x02], buf.header());
+/// assert_eq!(b"hi", buf.payload());
+/// assert_eq!(&[0x81, 0x02, b'h', b'i'], buf.as_bytes());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FrameBuf {
+    buf: Vec<u8>,
+    head_len: usize,
+}
+
+impl FrameBuf {
+    /// Encodes a frame holding `payload`, masked with `mask` if one is given.
+    pub fn new(head: &Head, payload: &[u8], mask: Option<[u8; 4]>) -> Result<Self, FrameError> {
+        let mut buf = Vec::new();
+        encode_to_vec(head, payload, mask, &mut buf)?;
+        Ok(Self {
+            head_len: buf.len() - payload.len(),
+            buf,
+        })
+    }
+
+    /// Returns the whole encoded frame.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the encoded header.
+    pub fn header(&self) -> &[u8] {
+        &self.buf[..self.head_len]
+    }
+
+    /// Returns the payload as it's sent, so masked if the frame is.
+    pub fn payload(&self) -> &[u8] {
+        &self.buf[self.head_len..]
+    }
+
+    /// Decodes the header back into a `Frame`.
+    ///
+    /// The header is decoded without checking it, so this also works for
+    /// frames that [`Frame::decode`] would reject, such as a Ping longer
+    /// than 125 bytes.
+    pub fn frame(&self) -> Frame {
+        let mut frame = Frame::empty();
+        // The header is complete, so this always returns `Status::Complete`.
+        frame.decode_unchecked(self.header());
+        frame
+    }
+
+    /// Returns the encoded frame's bytes.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl AsRef<[u8]> for FrameBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Opcode;
+
+    #[test]
+    fn masked_parts() {
+        let head = Head {
+            op: Opcode::Binary,
+            finished: false,
+            rsv: [false; 3],
+        };
+        let buf = FrameBuf::new(&head, &[0; 200], Some([1, 2, 3, 4])).unwrap();
+        assert_eq!(8, buf.header().len());
+        assert_eq!(&[1, 2, 3, 4, 1], &buf.payload()[..5]);
+
+        let frame = buf.frame();
+        assert_eq!(Some(head), frame.head);
+        assert_eq!(Some(200), frame.payload_len);
+        assert_eq!(Some([1, 2, 3, 4]), frame.mask);
+    }
+
+    #[test]
+    fn decodes_unchecked_headers() {
+        let head = Head {
+            op: Opcode::Ping,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let frame = FrameBuf::new(&head, &[0; 200], None).unwrap().frame();
+        assert_eq!(Some(head), frame.head);
+        assert_eq!(Some(200), frame.payload_len);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{encode_head_as, FrameError, Head, Opcode, PayloadLen};
+
+/// Builds a frame header, checking it against the framing rules before
+/// encoding it.
+///
+/// The header is finished and unmasked unless set otherwise, and no RSV bit
+/// may be set unless allowed with [`allow_rsv`](FrameBuilder::allow_rsv).
+///
+/// # Example
+/// ```
+/// use ws_frame::{FrameBuilder, FrameError};
+///
+/// let mut out = [0; 14];
+/// let len = FrameBuilder::text()
+///     .mask([1, 2, 3, 4])
+///     .payload_len(5)
+///     .encode(&mut out)
+///     .unwrap();
+/// assert_eq!(&[0x81, 0x85, 1, 2, 3, 4], &out[..len]);
+///
+/// assert_eq!(
+///     Err(FrameError::FragmentedControl),
+///     FrameBuilder::ping().fin(false).encode(&mut out)
+/// );
+/// ```
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct FrameBuilder {
+    op: Opcode,
+    finished: bool,
+    rsv: [bool; 3],
+    allowed_rsv: [bool; 3],
+    mask: Option<[u8; 4]>,
+    payload_len: PayloadLen,
+}
+
+impl FrameBuilder {
+    /// Starts building a header with the opcode `op`.
+    pub fn new(op: Opcode) -> Self {
+        Self {
+            op,
+            finished: true,
+            rsv: [false; 3],
+            allowed_rsv: [false; 3],
+            mask: None,
+            payload_len: PayloadLen::Short(0),
+        }
+    }
+
+    /// Starts building a Text frame header.
+    pub fn text() -> Self {
+        Self::new(Opcode::Text)
+    }
+
+    /// Starts building a Binary frame header.
+    pub fn binary() -> Self {
+        Self::new(Opcode::Binary)
+    }
+
+    /// Starts building a continuation frame header.
+    pub fn continuation() -> Self {
+        Self::new(Opcode::Continue)
+    }
+
+    /// Starts building a Close frame header.
+    pub fn close() -> Self {
+        Self::new(Opcode::Close)
+    }
+
+    /// Starts building a Ping frame header.
+    pub fn ping() -> Self {
+        Self::new(Opcode::Ping)
+    }
+
+    /// Starts building a Pong frame header.
+    pub fn pong() -> Self {
+        Self::new(Opcode::Pong)
+    }
+
+    /// Sets whether this is the final fragment of a message.
+    pub fn fin(mut self, fin: bool) -> Self {
+        self.finished = fin;
+        self
+    }
+
+    /// Sets the RSV bits.
+    pub fn rsv(mut self, rsv: [bool; 3]) -> Self {
+        self.rsv = rsv;
+        self
+    }
+
+    /// Allows the RSV bits that negotiated extensions define to be set.
+    pub fn allow_rsv(mut self, allowed: [bool; 3]) -> Self {
+        self.allowed_rsv = allowed;
+        self
+    }
+
+    /// Masks the frame with `key`, which clients must do.
+    pub fn mask(mut self, key: [u8; 4]) -> Self {
+        self.mask = Some(key);
+        self
+    }
+
+    /// Sets the length of the payload that follows the header.
+    pub fn payload_len(mut self, len: u64) -> Self {
+        self.payload_len = PayloadLen::minimal(len);
+        self
+    }
+
+    /// Sets the length of the payload along with the form to encode it in,
+    /// even if a shorter one would do.
+    ///
+    /// See [`encode_head_as`](crate::encode_head_as).
+    pub fn wire_len(mut self, len: PayloadLen) -> Self {
+        self.payload_len = len;
+        self
+    }
+
+    /// Checks the header, returning it if it's valid.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::ReservedBits`] if an RSV bit is set without being
+    /// allowed, [`FrameError::InvalidOpcode`] for an opcode that doesn't fit
+    /// in 4 bits, and [`FrameError::FragmentedControl`] or
+    /// [`FrameError::ControlFrameTooLong`] for a control frame that isn't
+    /// finished or is longer than 125 bytes.
+    pub fn build(&self) -> Result<Head, FrameError> {
+        if self
+            .rsv
+            .iter()
+            .zip(&self.allowed_rsv)
+            .any(|(&set, &allowed)| set && !allowed)
+        {
+            return Err(FrameError::ReservedBits);
+        }
+        if self.op.as_u8() > 0xF {
+            return Err(FrameError::InvalidOpcode);
+        }
+        if self.op.is_control() {
+            if !self.finished {
+                return Err(FrameError::FragmentedControl);
+            }
+            if self.payload_len.value() > 125 {
+                return Err(FrameError::ControlFrameTooLong);
+            }
+        }
+        Ok(Head {
+            op: self.op,
+            finished: self.finished,
+            rsv: self.rsv,
+        })
+    }
+
+    /// Checks the header and encodes it into `out`, returning the amount of
+    /// bytes written.
+    ///
+    /// # Errors
+    /// Returns the errors of [`build`](FrameBuilder::build) and of
+    /// [`encode_head_as`](crate::encode_head_as).
+    pub fn encode(&self, out: &mut [u8]) -> Result<usize, FrameError> {
+        encode_head_as(&self.build()?, self.payload_len, self.mask, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_before_encoding() {
+        let mut out = [0; 14];
+        assert_eq!(
+            Err(FrameError::ControlFrameTooLong),
+            FrameBuilder::close().payload_len(126).encode(&mut out)
+        );
+        assert_eq!(
+            Err(FrameError::ReservedBits),
+            FrameBuilder::binary().rsv([true, false, false]).build()
+        );
+
+        let deflated = FrameBuilder::binary()
+            .rsv([true, false, false])
+            .allow_rsv([true, false, false])
+            .fin(false);
+        assert_eq!(Ok(2), deflated.encode(&mut out));
+        assert_eq!([0x42, 0x00], out[..2]);
+
+        let forced = FrameBuilder::ping().wire_len(PayloadLen::Extended64(1));
+        assert_eq!(Ok(10), forced.encode(&mut out));
+        assert_eq!([0x89, 0x7F, 0, 0, 0, 0, 0, 0, 0, 1], out[..10]);
+        assert_eq!(
+            Err(FrameError::InvalidLengthForm),
+            FrameBuilder::binary()
+                .wire_len(PayloadLen::Short(126))
+                .encode(&mut out)
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use core::fmt;
+
+use crate::encode::encode_control_into;
+use crate::{FrameError, Opcode};
+
+/// A status code sent in the payload of a Close frame.
+///
+/// Codes are converted from and into their `u16` value losslessly, see
+/// [RFC 6455 §7.4](https://tools.ietf.org/html/rfc6455#section-7.4).
+///
+/// # Example
+/// ```
+/// use ws_frame::CloseCode;
+///
+/// assert_eq!(CloseCode::GoingAway, CloseCode::from(1001));
+/// assert_eq!(4000, u16::from(CloseCode::Library(4000)));
+/// assert!(!CloseCode::NoStatus.is_allowed_on_wire());
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CloseCode {
+    /// 1000, the purpose of the connection was fulfilled.
+    Normal,
+    /// 1001, an endpoint is going away.
+    GoingAway,
+    /// 1002, the peer violated the protocol.
+    ProtocolError,
+    /// 1003, a type of data was received that can't be accepted.
+    Unsupported,
+    /// 1005, no status code was present. Never sent on the wire.
+    NoStatus,
+    /// 1006, the connection closed abnormally. Never sent on the wire.
+    Abnormal,
+    /// 1007, a message contained data inconsistent with its type.
+    InvalidPayload,
+    /// 1008, a message violated the endpoint's policy.
+    PolicyViolation,
+    /// 1009, a message was too big to process.
+    TooBig,
+    /// 1010, the server didn't negotiate a required extension.
+    MandatoryExtension,
+    /// 1011, the server hit an unexpected condition.
+    InternalError,
+    /// 1012, the server is restarting.
+    ServiceRestart,
+    /// 1013, the server is overloaded, try again later.
+    TryAgainLater,
+    /// 1014, a gateway received an invalid upstream response.
+    BadGateway,
+    /// 1015, the TLS handshake failed. Never sent on the wire.
+    TlsHandshake,
+    /// 3000-4999, a code defined by a library, framework or application.
+    Library(u16),
+    /// Any other code, reserved by the RFC or unassigned.
+    Reserved(u16),
+}
+
+impl CloseCode {
+    /// Returns true if an endpoint may send this code in a Close frame.
+    ///
+    /// This rejects 1005, 1006 and 1015, which only report a status locally,
+    /// as well as every reserved code.
+    pub fn is_allowed_on_wire(self) -> bool {
+        !matches!(
+            self,
+            CloseCode::NoStatus
+                | CloseCode::Abnormal
+                | CloseCode::TlsHandshake
+                | CloseCode::Reserved(_)
+        )
+    }
+
+    /// Returns a short explanation of this code, for logs and error messages.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::CloseCode;
+    ///
+    /// assert_eq!("going away", CloseCode::GoingAway.description());
+    /// assert_eq!("1009 (message too big)", CloseCode::from(1009).to_string());
+    /// ```
+    pub fn description(self) -> &'static str {
+        match self {
+            CloseCode::Normal => "normal closure",
+            CloseCode::GoingAway => "going away",
+            CloseCode::ProtocolError => "protocol error",
+            CloseCode::Unsupported => "unsupported data",
+            CloseCode::NoStatus => "no status received",
+            CloseCode::Abnormal => "abnormal closure",
+            CloseCode::InvalidPayload => "invalid frame payload data",
+            CloseCode::PolicyViolation => "policy violation",
+            CloseCode::TooBig => "message too big",
+            CloseCode::MandatoryExtension => "mandatory extension missing",
+            CloseCode::InternalError => "internal server error",
+            CloseCode::ServiceRestart => "service restart",
+            CloseCode::TryAgainLater => "try again later",
+            CloseCode::BadGateway => "bad gateway",
+            CloseCode::TlsHandshake => "TLS handshake failed",
+            CloseCode::Library(code) if code < 4000 => "registered library code",
+            CloseCode::Library(_) => "private application code",
+            CloseCode::Reserved(_) => "reserved code",
+        }
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", u16::from(*self), self.description())
+    }
+}
+
+impl From<u16> for CloseCode {
+    fn from(code: u16) -> Self {
+        match code {
+            1000 => CloseCode::Normal,
+            1001 => CloseCode::GoingAway,
+            1002 => CloseCode::ProtocolError,
+            1003 => CloseCode::Unsupported,
+            1005 => CloseCode::NoStatus,
+            1006 => CloseCode::Abnormal,
+            1007 => CloseCode::InvalidPayload,
+            1008 => CloseCode::PolicyViolation,
+            1009 => CloseCode::TooBig,
+            1010 => CloseCode::MandatoryExtension,
+            1011 => CloseCode::InternalError,
+            1012 => CloseCode::ServiceRestart,
+            1013 => CloseCode::TryAgainLater,
+            1014 => CloseCode::BadGateway,
+            1015 => CloseCode::TlsHandshake,
+            3000..=4999 => CloseCode::Library(code),
+            _ => CloseCode::Reserved(code),
+        }
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(code: CloseCode) -> Self {
+        match code {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::Unsupported => 1003,
+            CloseCode::NoStatus => 1005,
+            CloseCode::Abnormal => 1006,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::TooBig => 1009,
+            CloseCode::MandatoryExtension => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::ServiceRestart => 1012,
+            CloseCode::TryAgainLater => 1013,
+            CloseCode::BadGateway => 1014,
+            CloseCode::TlsHandshake => 1015,
+            CloseCode::Library(code) | CloseCode::Reserved(code) => code,
+        }
+    }
+}
+
+/// The longest reason that fits in a Close frame after its 2 byte code.
+pub const MAX_REASON_LEN: usize = 123;
+
+/// Truncates a close reason to at most [`MAX_REASON_LEN`] bytes.
+///
+/// The cut is made at a character boundary, so the result is always valid
+/// UTF-8 and fits in the payload of a Close frame.
+///
+/// # Example
+/// ```
+/// use ws_frame::truncate_reason;
+///
+/// let reason = "é".repeat(100);
+/// assert_eq!(122, truncate_reason(&reason).len());
+/// assert_eq!("bye", truncate_reason("bye"));
+/// ```
+pub fn truncate_reason(reason: &str) -> &str {
+    if reason.len() <= MAX_REASON_LEN {
+        return reason;
+    }
+
+    let mut end = MAX_REASON_LEN;
+    while !reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    &reason[..end]
+}
+
+/// Encodes a Close frame with a status code and reason into `out`.
+///
+/// A reason longer than [`MAX_REASON_LEN`] is cut short with
+/// [`truncate_reason`], so the frame always fits the control frame limit.
+/// The payload is masked with `mask` if one is given, which clients must do.
+/// Returns the amount of bytes written.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidCloseCode`] for a code that must not be sent,
+/// such as 1005, and [`FrameError::BufferTooSmall`] if the frame doesn't fit
+/// in `out`.
+///
+/// # Example
+/// ```
+/// use ws_frame::encode_close;
+///
+/// let mut out = [0; 16];
+/// let len = encode_close(1001, "bye", None, &mut out).unwrap();
+/// assert_eq!(&[0x88, 0x05, 0x03, 0xE9, b'b', b'y', b'e'], &out[..len]);
+/// ```
+pub fn encode_close(
+    code: u16,
+    reason: &str,
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    if !CloseCode::from(code).is_allowed_on_wire() {
+        return Err(FrameError::InvalidCloseCode);
+    }
+    let reason = truncate_reason(reason);
+
+    let mut payload = [0; 2 + MAX_REASON_LEN];
+    payload[..2].copy_from_slice(&code.to_be_bytes());
+    payload[2..2 + reason.len()].copy_from_slice(reason.as_bytes());
+    encode_control_into(Opcode::Close, &payload[..2 + reason.len()], mask, out)
+}
+
+/// Encodes the Close frame replying to a peer's Close into `out`.
+///
+/// `payload` is the unmasked payload of the received frame. A valid code is
+/// echoed back without its reason and an empty payload gets an empty reply.
+/// A reason that isn't UTF-8 is answered with [`CloseCode::InvalidPayload`],
+/// and any other payload [`parse_close`] rejects with
+/// [`CloseCode::ProtocolError`]. Returns the amount of bytes written.
+///
+/// # Errors
+/// Returns [`FrameError::BufferTooSmall`] if the reply doesn't fit in `out`.
+///
+/// # Example
+/// ```
+/// use ws_frame::encode_close_reply;
+///
+/// let mut out = [0; 8];
+/// let len = encode_close_reply(b"\x03\xE9bye", None, &mut out).unwrap();
+/// assert_eq!(&[0x88, 0x02, 0x03, 0xE9], &out[..len]);
+///
+/// let len = encode_close_reply(b"\x03\xED", None, &mut out).unwrap();
+/// assert_eq!(&[0x88, 0x02, 0x03, 0xEA], &out[..len]);
+/// ```
+pub fn encode_close_reply(
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    let code = match parse_close(payload) {
+        Ok((CloseCode::NoStatus, _)) => return encode_control_into(Opcode::Close, &[], mask, out),
+        Ok((code, _)) => code,
+        Err(FrameError::InvalidUtf8) => CloseCode::InvalidPayload,
+        Err(_) => CloseCode::ProtocolError,
+    };
+    encode_close(code.into(), "", mask, out)
+}
+
+/// Parses the unmasked payload of a Close frame into its code and reason.
+///
+/// An empty payload has no status, which is reported as
+/// [`CloseCode::NoStatus`] with an empty reason.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidCloseCode`] if the payload is a single byte
+/// or the code must not be sent on the wire, and [`FrameError::InvalidUtf8`]
+/// if the reason isn't valid UTF-8.
+///
+/// # Example
+/// ```
+/// use ws_frame::{parse_close, CloseCode};
+///
+/// assert_eq!(Ok((CloseCode::GoingAway, "bye")), parse_close(b"\x03\xE9bye"));
+/// assert_eq!(Ok((CloseCode::NoStatus, "")), parse_close(b""));
+/// ```
+pub fn parse_close(payload: &[u8]) -> Result<(CloseCode, &str), FrameError> {
+    let (code, reason) = match payload {
+        [] => return Ok((CloseCode::NoStatus, "")),
+        [_] => return Err(FrameError::InvalidCloseCode),
+        [hi, lo, reason @ ..] => (CloseCode::from(u16::from_be_bytes([*hi, *lo])), reason),
+    };
+
+    if !code.is_allowed_on_wire() {
+        return Err(FrameError::InvalidCloseCode);
+    }
+    let reason = core::str::from_utf8(reason).map_err(|_| FrameError::InvalidUtf8)?;
+    Ok((code, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_codes_round_trip() {
+        for code in 0..=u16::MAX {
+            let close = CloseCode::from(code);
+            assert_eq!(code, u16::from(close));
+            assert_eq!(
+                matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999),
+                close.is_allowed_on_wire()
+            );
+        }
+    }
+
+    #[test]
+    fn parses_close_payloads() {
+        assert_eq!(Err(FrameError::InvalidCloseCode), parse_close(&[0x03]));
+        assert_eq!(
+            Err(FrameError::InvalidCloseCode),
+            parse_close(&[0x03, 0xED])
+        );
+        assert_eq!(
+            Err(FrameError::InvalidUtf8),
+            parse_close(&[0x03, 0xE8, 0xC3])
+        );
+        assert_eq!(
+            Ok((CloseCode::Library(4000), "é")),
+            parse_close(&[0x0F, 0xA0, 0xC3, 0xA9])
+        );
+    }
+
+    #[test]
+    fn replies_to_close_frames() {
+        let mut out = [0; 8];
+        assert_eq!(Ok(2), encode_close_reply(&[], None, &mut out));
+        assert_eq!(&[0x88, 0x00], &out[..2]);
+
+        assert_eq!(Ok(8), encode_close_reply(&[0x03], Some([1; 4]), &mut out));
+        assert_eq!(&[0x88, 0x82, 1, 1, 1, 1, 0x02, 0xEB], &out);
+
+        assert_eq!(
+            Ok(4),
+            encode_close_reply(&[0x03, 0xE8, 0xFF], None, &mut out)
+        );
+        assert_eq!(&[0x88, 0x02, 0x03, 0xEF], &out[..4]);
+
+        assert_eq!(
+            Err(FrameError::BufferTooSmall),
+            encode_close_reply(&[0x03, 0xE8], None, &mut out[..3])
+        );
+    }
+
+    #[test]
+    fn truncates_at_char_boundary() {
+        let reason = "a".repeat(122) + "€";
+        assert_eq!(&reason[..122], truncate_reason(&reason));
+
+        let reason = "a".repeat(200);
+        assert_eq!(MAX_REASON_LEN, truncate_reason(&reason).len());
+    }
+
+    #[test]
+    fn close_frames_are_checked() {
+        let mut out = [0; 131];
+        assert_eq!(
+            Err(FrameError::InvalidCloseCode),
+            encode_close(1005, "", None, &mut out)
+        );
+        let reason = "a".repeat(MAX_REASON_LEN) + "é";
+        assert_eq!(Ok(131), encode_close(1000, &reason, Some([1; 4]), &mut out));
+        assert_eq!(
+            Err(FrameError::BufferTooSmall),
+            encode_close(1000, &reason, None, &mut out[..126])
+        );
+
+        assert_eq!(Ok(127), encode_close(1000, &reason, None, &mut out));
+        assert_eq!(0x7D, out[1]);
+        assert_eq!(truncate_reason(&reason).as_bytes(), &out[4..127]);
+    }
+}
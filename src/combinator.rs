@@ -0,0 +1,52 @@
+use crate::{Frame, FrameError, Status};
+
+/// An error returned when a frame couldn't be decoded from a complete input.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DecodeError {
+    /// The input ended before the frame header did.
+    Incomplete,
+    /// The frame was invalid.
+    Invalid(FrameError),
+}
+
+/// Decodes a frame header from the front of `buf`.
+///
+/// Returns the frame along with the length of its header, which is where the
+/// payload starts.
+///
+/// # Example
+/// ```
+/// use ws_frame::{decode, Opcode};
+///
+/// let (frame, len) = decode(&[0x89, 0x00]).unwrap();
+/// assert_eq!(Opcode::Ping, frame.head.unwrap().op);
+/// assert_eq!(2, len);
+/// ```
+pub fn decode(buf: &[u8]) -> Result<(Frame, usize), DecodeError> {
+    let mut frame = Frame::empty();
+    match frame.decode(buf) {
+        Status::Complete(len) => Ok((frame, len)),
+        Status::Partial(_) => Err(DecodeError::Incomplete),
+        Status::Error(e) => Err(DecodeError::Invalid(e)),
+    }
+}
+
+/// Parses a frame header from the front of `input`.
+///
+/// This has the shape expected by parser combinator frameworks such as nom
+/// and winnow: on success it returns the rest of the input, starting at the
+/// payload, along with the decoded `Frame`.
+///
+/// # Example
+/// ```
+/// use ws_frame::{parse, DecodeError};
+///
+/// let (rest, frame) = parse(&[0x82, 0x01, 0xFF]).unwrap();
+/// assert_eq!(&[0xFF], rest);
+/// assert_eq!(Some(1), frame.payload_len);
+///
+/// assert_eq!(Err(DecodeError::Incomplete), parse(&[0x82]).map(|_| ()));
+/// ```
+pub fn parse(input: &[u8]) -> Result<(&[u8], Frame), DecodeError> {
+    decode(input).map(|(frame, len)| (&input[len..], frame))
+}
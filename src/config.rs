@@ -0,0 +1,176 @@
+use crate::{ControlChecks, Frame, FrameError, Head, Opcode, Role, ValidationPolicy};
+
+/// Limits and checks for decoding frames from an untrusted peer.
+///
+/// A `DecoderConfig` is a [`ValidationPolicy`], so it's passed to
+/// [`Frame::decode_with`] or [`Decoder::feed_with`](crate::Decoder::feed_with).
+/// Lengths are checked as soon as they're read, so an oversized frame is
+/// rejected before any of its payload has to be buffered.
+///
+/// # Example
+/// ```
+/// use ws_frame::{DecoderConfig, Frame, FrameError, Role, Status};
+///
+/// let mut config = DecoderConfig {
+///     role: Some(Role::Client),
+///     max_payload: Some(1024),
+///     ..DecoderConfig::default()
+/// };
+///
+/// // A header announcing a 64KiB payload.
+/// let buf = [0x82, 0x7F, 0, 0, 0, 0, 0, 1, 0, 0];
+/// let mut f = Frame::empty();
+/// assert_eq!(
+///     Status::Error(FrameError::PayloadOverLimit),
+///     f.decode_with(&buf, &mut config)
+/// );
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct DecoderConfig {
+    /// Which end is decoding, to check masking against. Masking isn't
+    /// checked if this is `None`.
+    pub role: Option<Role>,
+    /// The longest payload accepted.
+    pub max_payload: Option<u64>,
+    /// The longest extended length field accepted: 0, 2 or 8 bytes.
+    ///
+    /// Setting this to 2 rejects the 64-bit length form outright.
+    pub max_extended_len: usize,
+    /// Whether control frames are checked as with [`ControlChecks`].
+    pub control_checks: bool,
+    /// The RSV bits that negotiated extensions define, which may be set.
+    /// Frames setting any other RSV bit are rejected.
+    ///
+    /// For example, `permessage-deflate` defines RSV1, so allows
+    /// `[true, false, false]`.
+    pub allowed_rsv: [bool; 3],
+    /// The reserved opcodes that negotiated extensions define, as a bit set
+    /// where bit `n` allows opcode `n`. Frames with any other reserved opcode
+    /// are rejected.
+    ///
+    /// For example, `1 << 0x3` accepts frames with opcode 0x3, which are
+    /// decoded as [`Opcode::Reserved(0x3)`](Opcode::Reserved).
+    pub allowed_opcodes: u16,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self {
+            role: None,
+            max_payload: None,
+            max_extended_len: 8,
+            control_checks: true,
+            allowed_rsv: [false; 3],
+            allowed_opcodes: 0,
+        }
+    }
+}
+
+impl ValidationPolicy for DecoderConfig {
+    fn rsv(&mut self, rsv: [bool; 3]) -> Result<(), FrameError> {
+        if rsv
+            .iter()
+            .zip(&self.allowed_rsv)
+            .any(|(&set, &allowed)| set && !allowed)
+        {
+            Err(FrameError::ReservedBits)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn length(&mut self, payload_len: u64, extended: usize) -> Result<(), FrameError> {
+        if extended > self.max_extended_len {
+            return Err(FrameError::LengthFormRejected);
+        }
+        match self.max_payload {
+            Some(max) if payload_len > max => Err(FrameError::PayloadOverLimit),
+            _ => Ok(()),
+        }
+    }
+
+    fn control(&mut self, head: &Head, payload_len: u64) -> Result<(), FrameError> {
+        if self.control_checks {
+            ControlChecks.control(head, payload_len)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn header(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        if let Some(Head {
+            op: Opcode::Reserved(op),
+            ..
+        }) = frame.head
+        {
+            if self.allowed_opcodes & (1 << op) == 0 {
+                return Err(FrameError::InvalidOpcode);
+            }
+        }
+        match &mut self.role {
+            Some(role) => role.header(frame),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Status;
+
+    #[test]
+    fn allows_negotiated_rsv_bits() {
+        let mut config = DecoderConfig {
+            allowed_rsv: [true, false, false],
+            ..DecoderConfig::default()
+        };
+        let mut f = Frame::empty();
+        assert!(f.decode_with(&[0xC1, 0x00], &mut config).is_complete());
+        assert_eq!(
+            Status::Error(FrameError::ReservedBits),
+            f.decode_with(&[0xE1, 0x00], &mut config)
+        );
+        assert_eq!(
+            Status::Error(FrameError::ReservedBits),
+            f.decode_with(&[0xC1, 0x00], &mut DecoderConfig::default())
+        );
+    }
+
+    #[test]
+    fn allows_extension_opcodes() {
+        let mut config = DecoderConfig {
+            allowed_opcodes: 1 << 0x3,
+            ..DecoderConfig::default()
+        };
+        let mut f = Frame::empty();
+        assert!(f.decode_with(&[0x83, 0x00], &mut config).is_complete());
+        assert_eq!(
+            Some(Opcode::Reserved(0x3)),
+            f.head.as_ref().map(|head| head.op)
+        );
+        assert_eq!(
+            Status::Error(FrameError::InvalidOpcode),
+            f.decode_with(&[0x84, 0x00], &mut config)
+        );
+        assert_eq!(
+            Status::Error(FrameError::InvalidOpcode),
+            f.decode_with(&[0x83, 0x00], &mut DecoderConfig::default())
+        );
+    }
+
+    #[test]
+    fn rejects_length_forms() {
+        let mut config = DecoderConfig {
+            max_extended_len: 0,
+            control_checks: false,
+            ..DecoderConfig::default()
+        };
+        let mut f = Frame::empty();
+        assert_eq!(
+            Status::Error(FrameError::LengthFormRejected),
+            f.decode_with(&[0x82, 0x7E, 0x01, 0x00], &mut config)
+        );
+        assert!(f.decode_with(&[0x09, 0x7D], &mut config).is_complete());
+    }
+}
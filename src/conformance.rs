@@ -0,0 +1,150 @@
+//! Checks which RFC 6455 behaviors a decoder configuration enforces.
+//!
+//! # Example
+//! ```
+//! use ws_frame::conformance::{self_test, Behavior};
+//! use ws_frame::AcceptAll;
+//!
+//! let report = self_test(&mut AcceptAll);
+//! assert!(report.enforced(Behavior::AcceptsValidFrames));
+//! assert!(!report.enforced(Behavior::RejectsReservedBits));
+//! ```
+
+use crate::{Frame, ValidationPolicy};
+
+/// A behavior required by RFC 6455.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Behavior {
+    /// Well-formed frames decode successfully.
+    AcceptsValidFrames,
+    /// Frames with RSV bits set are rejected when no extension defines them.
+    RejectsReservedBits,
+    /// Frames with a reserved opcode are rejected.
+    RejectsReservedOpcodes,
+    /// Control frames without FIN set are rejected.
+    RejectsFragmentedControl,
+    /// Control frames with a payload longer than 125 bytes are rejected.
+    RejectsOversizedControl,
+    /// Payload lengths not using the minimal encoding are rejected.
+    RejectsNonMinimalLength,
+    /// 64-bit payload lengths with the most significant bit set are rejected.
+    RejectsLengthMsb,
+}
+
+impl Behavior {
+    /// Every behavior checked by [`self_test`].
+    pub const ALL: [Behavior; 7] = [
+        Behavior::AcceptsValidFrames,
+        Behavior::RejectsReservedBits,
+        Behavior::RejectsReservedOpcodes,
+        Behavior::RejectsFragmentedControl,
+        Behavior::RejectsOversizedControl,
+        Behavior::RejectsNonMinimalLength,
+        Behavior::RejectsLengthMsb,
+    ];
+
+    /// The RFC 6455 section that requires this behavior.
+    pub fn section(self) -> &'static str {
+        match self {
+            Behavior::AcceptsValidFrames => "5.2",
+            Behavior::RejectsReservedBits => "5.2",
+            Behavior::RejectsReservedOpcodes => "5.2",
+            Behavior::RejectsFragmentedControl => "5.5",
+            Behavior::RejectsOversizedControl => "5.5",
+            Behavior::RejectsNonMinimalLength => "5.2",
+            Behavior::RejectsLengthMsb => "5.2",
+        }
+    }
+
+    fn cases(self) -> &'static [&'static [u8]] {
+        match self {
+            Behavior::AcceptsValidFrames => &[
+                &[0x81, 0x00],
+                &[0x82, 0x02, 0x01, 0x02],
+                &[0x01, 0x01, b'a'],
+                &[0x80, 0x01, b'b'],
+                &[0x89, 0x00],
+                &[0x8A, 0x01, 0x00],
+                &[0x88, 0x02, 0x03, 0xE8],
+                &[0x81, 0x81, 0x01, 0x02, 0x03, 0x04, 0x60],
+                &[0x82, 0x7E, 0x00, 0x7E],
+                &[0x82, 0x7F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00],
+            ],
+            Behavior::RejectsReservedBits => &[&[0xC1, 0x00], &[0xA1, 0x00], &[0x91, 0x00]],
+            Behavior::RejectsReservedOpcodes => &[&[0x83, 0x00], &[0x8B, 0x00]],
+            Behavior::RejectsFragmentedControl => &[&[0x09, 0x00], &[0x08, 0x00]],
+            Behavior::RejectsOversizedControl => &[&[0x89, 0x7E, 0x00, 0x7E]],
+            Behavior::RejectsNonMinimalLength => &[
+                &[0x82, 0x7E, 0x00, 0x05],
+                &[0x82, 0x7F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00],
+            ],
+            Behavior::RejectsLengthMsb => {
+                &[&[0x82, 0x7F, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]]
+            }
+        }
+    }
+}
+
+/// The outcome of [`self_test`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Report {
+    results: [(Behavior, bool); 7],
+}
+
+impl Report {
+    /// Returns whether `behavior` is enforced.
+    pub fn enforced(&self, behavior: Behavior) -> bool {
+        self.results
+            .iter()
+            .any(|&(b, enforced)| b == behavior && enforced)
+    }
+
+    /// Returns whether every behavior is enforced.
+    pub fn is_conformant(&self) -> bool {
+        self.results.iter().all(|&(_, enforced)| enforced)
+    }
+
+    /// Iterates over each behavior and whether it is enforced.
+    pub fn iter(&self) -> impl Iterator<Item = (Behavior, bool)> + '_ {
+        self.results.iter().copied()
+    }
+}
+
+/// Runs a battery of canned frames through a decoder using `policy`.
+pub fn self_test<P: ValidationPolicy + ?Sized>(policy: &mut P) -> Report {
+    let mut results = [(Behavior::AcceptsValidFrames, false); 7];
+
+    for (result, &behavior) in results.iter_mut().zip(Behavior::ALL.iter()) {
+        let enforced = behavior.cases().iter().all(|case| {
+            let status = Frame::empty().decode_with(case, policy);
+            match behavior {
+                Behavior::AcceptsValidFrames => status.is_complete(),
+                _ => status.is_error(),
+            }
+        });
+        *result = (behavior, enforced);
+    }
+
+    Report { results }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::tests::NoRsv;
+    use crate::AcceptAll;
+
+    #[test]
+    fn reports_enforced_behaviors() {
+        let report = self_test(&mut AcceptAll);
+        assert!(report.enforced(Behavior::AcceptsValidFrames));
+        assert!(!report.is_conformant());
+
+        let report = self_test(&mut NoRsv);
+        assert!(report.enforced(Behavior::AcceptsValidFrames));
+        assert!(report.enforced(Behavior::RejectsReservedBits));
+        assert!(!report.enforced(Behavior::RejectsFragmentedControl));
+        // Every policy gets this one, as the decoder itself enforces it.
+        assert!(report.enforced(Behavior::RejectsLengthMsb));
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{Frame, FrameError, Status};
+
+/// How much of a buffer is taken up by complete frames, as returned by
+/// [`count_frames`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct FrameCount {
+    /// The amount of complete frames, headers and payloads, at the start of
+    /// the buffer.
+    pub frames: usize,
+    /// The amount of bytes spanned by those frames.
+    pub len: usize,
+    /// The amount of bytes after them, which start a partial frame.
+    pub partial: usize,
+    /// The full length of the partial frame, if its header is complete.
+    pub partial_len: Option<u64>,
+}
+
+/// Counts the complete frames held in `buf`.
+///
+/// # Example
+/// ```
+/// use ws_frame::{count_frames, FrameCount};
+///
+/// // A whole Ping, then the first byte of a 3 byte text payload.
+/// let buf = [0x89, 0x00, 0x81, 0x03, b'a'];
+/// assert_eq!(
+///     Ok(FrameCount {
+///         frames: 1,
+///         len: 2,
+///         partial: 3,
+///         partial_len: Some(5),
+///     }),
+///     count_frames(&buf)
+/// );
+/// ```
+pub fn count_frames(buf: &[u8]) -> Result<FrameCount, FrameError> {
+    let mut count = FrameCount {
+        frames: 0,
+        len: 0,
+        partial: 0,
+        partial_len: None,
+    };
+
+    while count.len < buf.len() {
+        let mut frame = Frame::empty();
+        let head_len = match frame.decode(&buf[count.len..]) {
+            Status::Complete(len) => len,
+            Status::Partial(_) => break,
+            Status::Error(e) => return Err(e),
+        };
+
+        let frame_len = (head_len as u64).saturating_add(frame.payload_len.unwrap_or(0));
+        if frame_len > (buf.len() - count.len) as u64 {
+            count.partial_len = Some(frame_len);
+            break;
+        }
+        count.frames += 1;
+        count.len += frame_len as usize;
+    }
+
+    count.partial = buf.len() - count.len;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_partial_headers() {
+        let count = count_frames(&[0x82, 0x01, 0xFF, 0x82, 0x7E, 0x01]).unwrap();
+        assert_eq!(
+            (1, 3, 3, None),
+            (count.frames, count.len, count.partial, count.partial_len)
+        );
+
+        assert_eq!(Ok(0), count_frames(&[]).map(|c| c.frames));
+    }
+}
@@ -0,0 +1,296 @@
+use core::cmp;
+
+use crate::{
+    apply_mask_into, head_len, ControlChecks, Frame, FrameError, Sequenced, Sequencer, Status,
+    ValidationPolicy,
+};
+
+/// The longest frame header.
+const MAX_HEAD_LEN: usize = 14;
+
+/// Something decoded by a [`Decoder`].
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+    /// A frame header was decoded. Its payload follows as `Payload` events,
+    /// the last of them marked [`last`](Segment::last). A frame with an empty
+    /// payload, such as a bare Ping or Close, is complete with its header
+    /// and gets no `Payload` events.
+    Header(Frame),
+    /// Part of the current frame's payload, borrowed from the fed buffer.
+    Payload(Segment<'a>),
+}
+
+/// A run of payload bytes, as they were received.
+#[derive(Debug, PartialEq)]
+pub struct Segment<'a> {
+    /// The payload bytes, still masked if the frame is.
+    pub data: &'a [u8],
+    /// The position of `data` in the frame's payload.
+    pub offset: u64,
+    /// The frame's mask key.
+    pub mask: Option<[u8; 4]>,
+    /// Whether this segment ends the payload.
+    pub last: bool,
+}
+
+impl Segment<'_> {
+    /// Copies the unmasked payload bytes into the start of `out`.
+    ///
+    /// # Panics
+    /// Panics if `out` is shorter than `data`.
+    pub fn unmask_into(&self, out: &mut [u8]) {
+        let out = &mut out[..self.data.len()];
+        match self.mask {
+            Some(mut mask) => {
+                mask.rotate_left((self.offset % 4) as usize);
+                apply_mask_into(mask, self.data, out);
+            }
+            None => out.copy_from_slice(self.data),
+        }
+    }
+}
+
+/// A resumable decoder fed successive chunks of a byte stream.
+///
+/// Unlike [`Frame::decode`], a `Decoder` remembers where it is between
+/// calls. Bytes of a header split across chunks are kept internally, and
+/// payloads are handed out as they arrive instead of being buffered.
+///
+/// Each header is decoded once, when all of its bytes are held, so input
+/// trickling in a byte at a time is never parsed again from the start of the
+/// header, and a policy sees each header exactly once.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Decoder, Event};
+///
+/// let mut decoder = Decoder::new();
+/// let mut payload = Vec::new();
+///
+/// for chunk in [&[0x81, 0x03, b'a'][..], &[b'b', b'c', 0x89, 0x00][..]] {
+///     let mut buf = chunk;
+///     while !buf.is_empty() {
+///         let (used, event) = decoder.feed(buf).unwrap();
+///         if let Some(Event::Payload(segment)) = event {
+///             payload.extend_from_slice(segment.data);
+///         }
+///         buf = &buf[used..];
+///     }
+/// }
+/// assert_eq!(b"abc", &payload[..]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    remaining: u64,
+    offset: u64,
+    mask: Option<[u8; 4]>,
+    head: [u8; MAX_HEAD_LEN],
+    partial: usize,
+    seq: Sequencer,
+    current: u64,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Creates a `Decoder` positioned at the start of a frame.
+    pub const fn new() -> Self {
+        Self {
+            remaining: 0,
+            offset: 0,
+            mask: None,
+            head: [0; MAX_HEAD_LEN],
+            partial: 0,
+            seq: Sequencer::new(),
+            current: 0,
+        }
+    }
+
+    /// Returns the amount of payload bytes of the current frame still to
+    /// come.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Decodes the next event from `buf`.
+    ///
+    /// Returns the amount of bytes consumed, which the next call must start
+    /// after, along with the event if one was decoded. Bytes of an incomplete
+    /// header are consumed and kept, so `None` always means more input is
+    /// needed.
+    pub fn feed<'a>(&mut self, buf: &'a [u8]) -> Result<(usize, Option<Event<'a>>), FrameError> {
+        self.feed_with(buf, &mut ControlChecks)
+    }
+
+    /// Like [`feed`](Decoder::feed), but checks headers against `policy`.
+    pub fn feed_with<'a, P>(
+        &mut self,
+        buf: &'a [u8],
+        policy: &mut P,
+    ) -> Result<(usize, Option<Event<'a>>), FrameError>
+    where
+        P: ValidationPolicy + ?Sized,
+    {
+        if buf.is_empty() {
+            return Ok((0, None));
+        }
+        if self.remaining > 0 {
+            let n = cmp::min(self.remaining, buf.len() as u64) as usize;
+            let segment = Segment {
+                data: &buf[..n],
+                offset: self.offset,
+                mask: self.mask,
+                last: n as u64 == self.remaining,
+            };
+            self.remaining -= n as u64;
+            self.offset += n as u64;
+            return Ok((n, Some(Event::Payload(segment))));
+        }
+
+        let mut used = self.hold(buf, 2);
+        if self.partial < 2 {
+            return Ok((used, None));
+        }
+        used += self.hold(&buf[used..], head_len(self.head[1]));
+        let len = head_len(self.head[1]);
+        if self.partial < len {
+            return Ok((used, None));
+        }
+
+        let mut frame = Frame::empty();
+        match frame.decode_with(&self.head[..len], policy) {
+            Status::Complete(_) => {
+                self.partial = 0;
+                self.remaining = frame.payload_len.unwrap_or(0);
+                self.offset = 0;
+                self.mask = frame.mask;
+                self.current = self.seq.next_seq();
+                Ok((used, Some(Event::Header(frame))))
+            }
+            Status::Partial(_) => unreachable!("held a complete header"),
+            Status::Error(e) => Err(e),
+        }
+    }
+
+    /// Like [`feed_with`](Decoder::feed_with), but tags each event with the
+    /// sequence number of the frame it belongs to, and an error with the
+    /// number of the frame whose header was rejected.
+    ///
+    /// Frames are numbered from 0 in the order their headers are decoded.
+    pub fn feed_sequenced<'a, P>(
+        &mut self,
+        buf: &'a [u8],
+        policy: &mut P,
+    ) -> Result<(usize, Option<Sequenced<Event<'a>>>), Sequenced<FrameError>>
+    where
+        P: ValidationPolicy + ?Sized,
+    {
+        match self.feed_with(buf, policy) {
+            Ok((used, event)) => {
+                let seq = self.current;
+                Ok((used, event.map(|value| Sequenced { seq, value })))
+            }
+            Err(value) => Err(Sequenced {
+                seq: self.seq.peek(),
+                value,
+            }),
+        }
+    }
+
+    /// Keeps bytes from `buf` until `target` header bytes are held, returning
+    /// how many were taken.
+    fn hold(&mut self, buf: &[u8], target: usize) -> usize {
+        let n = cmp::min(target.saturating_sub(self.partial), buf.len());
+        self.head[self.partial..self.partial + n].copy_from_slice(&buf[..n]);
+        self.partial += n;
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_at_a_time() {
+        let buf = [
+            0x82,
+            0x85,
+            1,
+            2,
+            3,
+            4,
+            1 ^ 10,
+            2 ^ 20,
+            3 ^ 30,
+            4 ^ 40,
+            1 ^ 50,
+        ];
+        let mut decoder = Decoder::new();
+        let mut headers = 0;
+        let mut payload = [0; 5];
+        let mut filled = 0;
+
+        for b in buf.chunks(1) {
+            match decoder.feed(b).unwrap() {
+                (1, Some(Event::Header(frame))) => {
+                    assert_eq!(Some(5), frame.payload_len);
+                    headers += 1;
+                }
+                (1, Some(Event::Payload(segment))) => {
+                    let mut out = [0];
+                    segment.unmask_into(&mut out);
+                    payload[filled] = out[0];
+                    filled += 1;
+                    assert_eq!(filled == 5, segment.last);
+                }
+                (1, None) => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert_eq!(1, headers);
+        assert_eq!([10, 20, 30, 40, 50], payload);
+        assert_eq!(0, decoder.remaining());
+    }
+
+    #[test]
+    fn decodes_each_header_once() {
+        struct Count(usize);
+
+        impl ValidationPolicy for Count {
+            fn rsv(&mut self, _: [bool; 3]) -> Result<(), FrameError> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+
+        let buf = [0x82, 0x7F, 0, 0, 0, 0, 0, 0, 0, 0, 0x89, 0x00];
+        let mut decoder = Decoder::new();
+        let mut count = Count(0);
+        for b in buf.chunks(1) {
+            assert_eq!(1, decoder.feed_with(b, &mut count).unwrap().0);
+        }
+        assert_eq!(2, count.0);
+    }
+
+    #[test]
+    fn numbers_frames() {
+        let buf = [0x82, 0x01, 7, 0x89, 0x00];
+        let mut decoder = Decoder::new();
+        let mut seqs = [0; 3];
+        let mut buf = &buf[..];
+        for seq in &mut seqs {
+            let (used, event) = decoder.feed_sequenced(buf, &mut ControlChecks).unwrap();
+            *seq = event.unwrap().seq;
+            buf = &buf[used..];
+        }
+        assert_eq!([0, 0, 1], seqs);
+
+        let err = decoder.feed_sequenced(&[0x89, 0x7E, 0, 126], &mut ControlChecks);
+        assert_eq!(2, err.unwrap_err().seq);
+    }
+}
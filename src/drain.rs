@@ -0,0 +1,113 @@
+use crate::{Frame, FrameError, Opcode, Scanner};
+
+/// The result of feeding bytes to a [`Drain`].
+#[derive(Debug, PartialEq)]
+pub enum Drained {
+    /// The peer's Close frame has not arrived yet.
+    ///
+    /// Contains the amount of bytes consumed. As with [`Scanner::scan`], any
+    /// bytes after that must be passed again with the next call.
+    Pending(usize),
+    /// The header of the peer's Close frame was decoded.
+    ///
+    /// `consumed` is the offset of its payload in the buffer that was fed.
+    Closed {
+        /// The amount of bytes consumed, up to the end of the Close header.
+        consumed: usize,
+        /// The decoded Close frame header.
+        frame: Frame,
+    },
+    /// More bytes than the limit were drained without a Close frame arriving.
+    LimitExceeded,
+}
+
+/// Drains incoming frames after the local side has sent a Close frame.
+///
+/// RFC 6455 §7.1.1 asks an endpoint that sent Close to keep reading until the
+/// peer's Close arrives. A `Drain` decodes and discards everything up to
+/// that point, optionally surfacing each header, and gives up once more than
+/// a caller chosen amount of bytes has been drained.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Drain, Drained};
+///
+/// let mut drain = Drain::new(1024);
+///
+/// // A late text frame, followed by the peer's Close with code 1000.
+/// let buf = [0x81, 0x01, b'a', 0x88, 0x02, 0x03, 0xE8];
+/// match drain.feed(&buf, |_| {}).unwrap() {
+///     Drained::Closed { consumed, .. } => assert_eq!(&[0x03, 0xE8], &buf[consumed..]),
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Drain {
+    scanner: Scanner,
+    drained: u64,
+    limit: u64,
+}
+
+impl Drain {
+    /// Creates a `Drain` that gives up after `limit` bytes.
+    pub const fn new(limit: u64) -> Self {
+        Self {
+            scanner: Scanner::new(),
+            drained: 0,
+            limit,
+        }
+    }
+
+    /// Returns the amount of bytes drained so far.
+    pub fn drained(&self) -> u64 {
+        self.drained
+    }
+
+    /// Feeds the next bytes read from the peer, calling `on_frame` with the
+    /// header of every frame discarded before its Close.
+    pub fn feed<F>(&mut self, buf: &[u8], mut on_frame: F) -> Result<Drained, FrameError>
+    where
+        F: FnMut(&Frame),
+    {
+        let mut close = None;
+        let (consumed, closed) = self.scanner.scan_while(buf, |frame| {
+            if let Some(Opcode::Close) = frame.head.as_ref().map(|head| head.op) {
+                close = Some(frame.clone());
+                false
+            } else {
+                on_frame(frame);
+                true
+            }
+        })?;
+        paranoid_assert!(consumed <= buf.len());
+        paranoid_assert!(closed == close.is_some(), "stopped without a Close frame");
+        self.drained = self.drained.saturating_add(consumed as u64);
+
+        match close {
+            Some(frame) if closed => Ok(Drained::Closed { consumed, frame }),
+            _ if self.drained > self.limit => Ok(Drained::LimitExceeded),
+            _ => Ok(Drained::Pending(consumed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gives_up_after_limit() {
+        let mut drain = Drain::new(4);
+        let mut frames = 0;
+
+        assert_eq!(
+            Ok(Drained::Pending(3)),
+            drain.feed(&[0x82, 0x01, 0x00], |_| frames += 1)
+        );
+        assert_eq!(
+            Ok(Drained::LimitExceeded),
+            drain.feed(&[0x89, 0x00], |_| frames += 1)
+        );
+        assert_eq!(2, frames);
+    }
+}
@@ -0,0 +1,761 @@
+use core::mem::MaybeUninit;
+
+use crate::{apply_mask, FrameError, Head, MaskKeyGenerator, Opcode, PayloadLen};
+
+/// The length of the largest control frame: a 2 byte head, a 4 byte mask and
+/// a 125 byte payload.
+pub const MAX_CONTROL_FRAME_LEN: usize = 131;
+
+/// The length of the largest frame header.
+const MAX_HEAD_LEN: usize = 14;
+
+/// An unmasked Ping frame with an empty payload, as sent by a server.
+pub const EMPTY_PING: [u8; 2] = [0x89, 0x00];
+
+/// An unmasked Pong frame with an empty payload, as sent by a server.
+pub const EMPTY_PONG: [u8; 2] = [0x8A, 0x00];
+
+/// An unmasked Close frame with status code 1000 and no reason, as sent by a
+/// server.
+pub const CLOSE_NORMAL: [u8; 4] = [0x88, 0x02, 0x03, 0xE8];
+
+/// Encodes a control frame entirely on the stack.
+///
+/// Returns the encoded bytes along with how many of them were used. The
+/// payload is masked with `mask` if one is given, which clients must do.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] if `op` isn't Close, Ping or Pong,
+/// and [`FrameError::ControlFrameTooLong`] if `payload` is longer than 125
+/// bytes.
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_control, Opcode};
+///
+/// let (buf, len) = encode_control(Opcode::Pong, b"hi", None).unwrap();
+/// assert_eq!(&[0x8A, 0x02, b'h', b'i'], &buf[..len]);
+/// ```
+pub fn encode_control(
+    op: Opcode,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+) -> Result<([u8; MAX_CONTROL_FRAME_LEN], usize), FrameError> {
+    match op {
+        Opcode::Close | Opcode::Ping | Opcode::Pong => {}
+        _ => return Err(FrameError::InvalidOpcode),
+    }
+    if payload.len() > 125 {
+        return Err(FrameError::ControlFrameTooLong);
+    }
+
+    let head = Head {
+        op,
+        finished: true,
+        rsv: [false; 3],
+    };
+    let (head, len) = write_head(&head, payload.len() as u64, mask)?;
+
+    let mut buf = [0; MAX_CONTROL_FRAME_LEN];
+    buf[..len].copy_from_slice(&head[..len]);
+    buf[len..len + payload.len()].copy_from_slice(payload);
+    if let Some(mask) = mask {
+        apply_mask(mask, &mut buf[len..len + payload.len()]);
+    }
+
+    Ok((buf, len + payload.len()))
+}
+
+/// Encodes a Ping frame carrying `payload` into `out`.
+///
+/// The payload is masked with `mask` if one is given, which clients must do.
+/// Returns the amount of bytes written.
+///
+/// # Errors
+/// Returns [`FrameError::ControlFrameTooLong`] if `payload` is longer than
+/// 125 bytes, and [`FrameError::BufferTooSmall`] if the frame doesn't fit in
+/// `out`.
+///
+/// # Example
+/// ```
+/// use ws_frame::encode_ping;
+///
+/// let mut out = [0; 8];
+/// let len = encode_ping(b"hi", None, &mut out).unwrap();
+/// assert_eq!(&[0x89, 0x02, b'h', b'i'], &out[..len]);
+/// ```
+pub fn encode_ping(
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    encode_control_into(Opcode::Ping, payload, mask, out)
+}
+
+/// Encodes the Pong answering a Ping into `out`.
+///
+/// RFC 6455 §5.5.3 asks for a Pong to carry the same application data as the
+/// Ping it answers, so `ping_payload` must be the Ping's payload once
+/// unmasked. The Pong is masked with `mask` if one is given.
+///
+/// # Errors
+/// Returns the errors of [`encode_ping`].
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_pong_for, Frame};
+///
+/// let ping = [0x89, 0x02, b'h', b'i'];
+/// let mut f = Frame::empty();
+/// f.decode(&ping);
+///
+/// let mut out = [0; 8];
+/// let len = encode_pong_for(f.payload(&ping).unwrap(), None, &mut out).unwrap();
+/// assert_eq!(&[0x8A, 0x02, b'h', b'i'], &out[..len]);
+/// ```
+pub fn encode_pong_for(
+    ping_payload: &[u8],
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    encode_control_into(Opcode::Pong, ping_payload, mask, out)
+}
+
+/// Encodes a control frame into `out`, as with [`encode_control`].
+pub(crate) fn encode_control_into(
+    op: Opcode,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    let (buf, len) = encode_control(op, payload, mask)?;
+    out.get_mut(..len)
+        .ok_or(FrameError::BufferTooSmall)?
+        .copy_from_slice(&buf[..len]);
+    Ok(len)
+}
+
+/// Encodes a frame header into `out`, for a payload sent separately.
+///
+/// The length is written in its shortest form, followed by `mask` if one is
+/// given. Returns the amount of bytes written, at most 14.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit in 4
+/// bits, [`FrameError::LengthMsb`] for a length of 2^63 or more, which can't
+/// be sent, and [`FrameError::BufferTooSmall`] if the header doesn't fit in
+/// `out`.
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_head, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Binary, finished: true, rsv: [false; 3] };
+/// let mut out = [0; 14];
+///
+/// let len = encode_head(&head, 256, None, &mut out).unwrap();
+/// assert_eq!(&[0x82, 0x7E, 0x01, 0x00], &out[..len]);
+/// ```
+pub fn encode_head(
+    head: &Head,
+    payload_len: u64,
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    encode_head_as(head, PayloadLen::minimal(payload_len), mask, out)
+}
+
+/// Encodes a frame header into `out`, writing the length in the form given
+/// by `payload_len` instead of the shortest one.
+///
+/// This is for interop and fuzz testing, as RFC 6455 asks for the shortest
+/// form: [`Frame::decode_strict`](crate::Frame::decode_strict) rejects the
+/// result with [`FrameError::NonMinimalLength`], and
+/// [`Frame::lint`](crate::Frame::lint) reports it.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidLengthForm`] for a [`PayloadLen::Short`]
+/// over 125, and otherwise the errors of [`encode_head`].
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_head_as, Frame, FrameError, Head, Opcode, PayloadLen, Status};
+///
+/// let head = Head { op: Opcode::Binary, finished: true, rsv: [false; 3] };
+/// let mut out = [0; 14];
+///
+/// let len = encode_head_as(&head, PayloadLen::Extended16(5), None, &mut out).unwrap();
+/// assert_eq!(&[0x82, 0x7E, 0x00, 0x05], &out[..len]);
+///
+/// let mut f = Frame::empty();
+/// assert_eq!(Status::Error(FrameError::NonMinimalLength), f.decode_strict(&out[..len]));
+/// ```
+pub fn encode_head_as(
+    head: &Head,
+    payload_len: PayloadLen,
+    mask: Option<[u8; 4]>,
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    let (head, len) = write_head_as(head, payload_len, mask)?;
+    out.get_mut(..len)
+        .ok_or(FrameError::BufferTooSmall)?
+        .copy_from_slice(&head[..len]);
+    Ok(len)
+}
+
+/// An encoded frame header, kept on the stack.
+///
+/// This never touches the heap, so suits `no_std` targets without `alloc`,
+/// and the header can be copied into a DMA buffer with
+/// [`as_bytes`](HeaderBuf::as_bytes).
+///
+/// # Example
+/// ```
+/// use ws_frame::{HeaderBuf, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Binary, finished: true, rsv: [false; 3] };
+/// let header = HeaderBuf::new(&head, 300, Some([1, 2, 3, 4])).unwrap();
+/// assert_eq!(&[0x82, 0xFE, 0x01, 0x2C, 1, 2, 3, 4], header.as_bytes());
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct HeaderBuf {
+    buf: [u8; MAX_HEAD_LEN],
+    len: usize,
+}
+
+impl HeaderBuf {
+    /// Encodes the header of a frame with a `payload_len` byte payload.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit
+    /// in 4 bits, and [`FrameError::LengthMsb`] for a length of 2^63 or more.
+    pub fn new(head: &Head, payload_len: u64, mask: Option<[u8; 4]>) -> Result<Self, FrameError> {
+        let (buf, len) = write_head(head, payload_len, mask)?;
+        Ok(Self { buf, len })
+    }
+
+    /// Returns the encoded header.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns the length of the encoded header, from 2 to 14 bytes.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl AsRef<[u8]> for HeaderBuf {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// A frame encoded for a vectored write, as returned by [`encode_vectored`].
+///
+/// The header is held on the stack, and the payload is borrowed rather than
+/// copied after it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VectoredFrame<'a> {
+    /// The encoded header.
+    pub header: HeaderBuf,
+    /// The payload, masked if the frame is.
+    pub payload: &'a [u8],
+}
+
+impl VectoredFrame<'_> {
+    /// Returns the header and payload as slices for
+    /// [`Write::write_vectored`](std::io::Write::write_vectored).
+    #[cfg(feature = "std")]
+    pub fn io_slices(&self) -> [std::io::IoSlice<'_>; 2] {
+        [
+            std::io::IoSlice::new(self.header.as_bytes()),
+            std::io::IoSlice::new(self.payload),
+        ]
+    }
+}
+
+/// Encodes an unmasked frame for a vectored write, without copying
+/// `payload`.
+///
+/// With `std`, [`VectoredFrame::io_slices`] hands both parts to
+/// `write_vectored` at once.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit in 4
+/// bits, and [`FrameError::LengthMsb`] for a payload of 2^63 bytes or more.
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_vectored, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Binary, finished: true, rsv: [false; 3] };
+/// let payload = vec![0; 1 << 20];
+/// let frame = encode_vectored(&head, &payload).unwrap();
+///
+/// assert_eq!(&[0x82, 0x7F, 0, 0, 0, 0, 0, 0x10, 0, 0], frame.header.as_bytes());
+/// assert_eq!(payload.len(), frame.payload.len());
+/// ```
+pub fn encode_vectored<'a>(
+    head: &Head,
+    payload: &'a [u8],
+) -> Result<VectoredFrame<'a>, FrameError> {
+    Ok(VectoredFrame {
+        header: HeaderBuf::new(head, payload.len() as u64, None)?,
+        payload,
+    })
+}
+
+/// Encodes a client frame for a vectored write, masking `payload` in place
+/// with `key`.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit in 4
+/// bits, and [`FrameError::LengthMsb`] for a payload of 2^63 bytes or more,
+/// in which cases `payload` is left untouched.
+pub fn encode_vectored_masked<'a>(
+    head: &Head,
+    payload: &'a mut [u8],
+    key: [u8; 4],
+) -> Result<VectoredFrame<'a>, FrameError> {
+    let mut buf = [0; MAX_HEAD_LEN];
+    let len = encode_masked_in_place(head, payload, key, &mut buf)?;
+    Ok(VectoredFrame {
+        header: HeaderBuf { buf, len },
+        payload,
+    })
+}
+
+/// Encodes a client frame into `out`, masking the payload with `key`.
+///
+/// Returns the amount of bytes written.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit in 4
+/// bits, [`FrameError::LengthMsb`] for a payload of 2^63 bytes or more, and
+/// [`FrameError::BufferTooSmall`] if the frame doesn't fit in `out`.
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_masked, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Text, finished: true, rsv: [false; 3] };
+/// let mut out = [0; 16];
+///
+/// let len = encode_masked(&head, b"hi", [1, 2, 3, 4], &mut out).unwrap();
+/// assert_eq!(&[0x81, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2], &out[..len]);
+/// ```
+pub fn encode_masked(
+    head: &Head,
+    payload: &[u8],
+    key: [u8; 4],
+    out: &mut [u8],
+) -> Result<usize, FrameError> {
+    let len = encode_head(head, payload.len() as u64, Some(key), out)?;
+    let out = out
+        .get_mut(len..len + payload.len())
+        .ok_or(FrameError::BufferTooSmall)?;
+    out.copy_from_slice(payload);
+    apply_mask(key, out);
+    Ok(len + payload.len())
+}
+
+/// Encodes a client frame into `out`, masked with the next key from `keys`.
+///
+/// This is [`encode_masked`] with a fresh key, as clients must use for each
+/// frame.
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_masked_with, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Ping, finished: true, rsv: [false; 3] };
+/// let mut keys = || [1, 2, 3, 4];
+/// let mut out = [0; 6];
+///
+/// assert_eq!(Ok(6), encode_masked_with(&head, &[], &mut keys, &mut out));
+/// assert_eq!([0x89, 0x80, 1, 2, 3, 4], out);
+/// ```
+pub fn encode_masked_with<K>(
+    head: &Head,
+    payload: &[u8],
+    keys: &mut K,
+    out: &mut [u8],
+) -> Result<usize, FrameError>
+where
+    K: MaskKeyGenerator + ?Sized,
+{
+    encode_masked(head, payload, keys.next_key(), out)
+}
+
+/// Masks `payload` in place with `key`, and encodes the header of a client
+/// frame carrying it into `head_out`.
+///
+/// This avoids copying the payload, which can then be written after the
+/// header with a vectored write. Returns the amount of header bytes written.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit in 4
+/// bits, and [`FrameError::LengthMsb`] for a payload of 2^63 bytes or more,
+/// in which cases `payload` is left untouched.
+///
+/// # Example
+/// ```
+/// use ws_frame::{encode_masked_in_place, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Binary, finished: true, rsv: [false; 3] };
+/// let mut payload = [0xFF; 2];
+/// let mut out = [0; 14];
+///
+/// let len = encode_masked_in_place(&head, &mut payload, [1, 2, 3, 4], &mut out).unwrap();
+/// assert_eq!(&[0x82, 0x82, 1, 2, 3, 4], &out[..len]);
+/// assert_eq!([0xFE, 0xFD], payload);
+/// ```
+pub fn encode_masked_in_place(
+    head: &Head,
+    payload: &mut [u8],
+    key: [u8; 4],
+    head_out: &mut [u8; MAX_HEAD_LEN],
+) -> Result<usize, FrameError> {
+    let len = encode_head(head, payload.len() as u64, Some(key), head_out)?;
+    apply_mask(key, payload);
+    Ok(len)
+}
+
+/// Encodes a frame into a possibly uninitialized buffer.
+///
+/// The header and the payload, masked with `mask` if one is given, are
+/// written to the start of `out`. Returns the amount of bytes written, all of
+/// which are initialized.
+///
+/// # Errors
+/// Returns [`FrameError::InvalidOpcode`] for an opcode that doesn't fit in 4
+/// bits, [`FrameError::LengthMsb`] for a payload of 2^63 bytes or more, and
+/// [`FrameError::BufferTooSmall`] if the frame doesn't fit in `out`.
+///
+/// # Example
+/// ```
+/// use std::mem::MaybeUninit;
+/// use ws_frame::{encode_uninit, Head, Opcode};
+///
+/// let head = Head { op: Opcode::Text, finished: true, rsv: [false; 3] };
+/// let mut out = [MaybeUninit::uninit(); 16];
+///
+/// let len = encode_uninit(&head, b"hi", Some([1, 2, 3, 4]), &mut out).unwrap();
+/// assert_eq!(8, len);
+/// ```
+pub fn encode_uninit(
+    head: &Head,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+    out: &mut [MaybeUninit<u8>],
+) -> Result<usize, FrameError> {
+    let (head, len) = write_head(head, payload.len() as u64, mask)?;
+    let total = len
+        .checked_add(payload.len())
+        .filter(|&total| total <= out.len())
+        .ok_or(FrameError::BufferTooSmall)?;
+
+    for (out, &b) in out.iter_mut().zip(&head[..len]) {
+        *out = MaybeUninit::new(b);
+    }
+    let body = &mut out[len..total];
+    for (out, &b) in body.iter_mut().zip(payload) {
+        *out = MaybeUninit::new(b);
+    }
+    if let Some(mask) = mask {
+        // SAFETY: every byte of `body` was just initialized, and
+        // `MaybeUninit<u8>` has the same layout as `u8`.
+        let body = unsafe { &mut *(body as *mut [MaybeUninit<u8>] as *mut [u8]) };
+        apply_mask(mask, body);
+    }
+
+    Ok(total)
+}
+
+/// Appends an encoded frame to `out`.
+#[cfg(feature = "std")]
+pub(crate) fn encode_to_vec(
+    head: &Head,
+    payload: &[u8],
+    mask: Option<[u8; 4]>,
+    out: &mut Vec<u8>,
+) -> Result<(), FrameError> {
+    let (head, len) = write_head(head, payload.len() as u64, mask)?;
+    out.reserve(len + payload.len());
+    out.extend_from_slice(&head[..len]);
+
+    let start = out.len();
+    out.extend_from_slice(payload);
+    if let Some(mask) = mask {
+        apply_mask(mask, &mut out[start..]);
+    }
+    Ok(())
+}
+
+/// Writes a frame header using the minimal length encoding.
+fn write_head(
+    head: &Head,
+    payload_len: u64,
+    mask: Option<[u8; 4]>,
+) -> Result<([u8; MAX_HEAD_LEN], usize), FrameError> {
+    write_head_as(head, PayloadLen::minimal(payload_len), mask)
+}
+
+/// Writes a frame header using the length encoding of `payload_len`.
+fn write_head_as(
+    head: &Head,
+    payload_len: PayloadLen,
+    mask: Option<[u8; 4]>,
+) -> Result<([u8; MAX_HEAD_LEN], usize), FrameError> {
+    let op = head.op.as_u8();
+    if op > 0xF {
+        return Err(FrameError::InvalidOpcode);
+    }
+    // RFC 6455 §5.2 requires the most significant bit of a 64-bit length to
+    // be 0, and decoders reject frames that set it.
+    if payload_len.value() >> 63 != 0 {
+        return Err(FrameError::LengthMsb);
+    }
+
+    let mut buf = [0; MAX_HEAD_LEN];
+    buf[0] = (head.finished as u8) << 7
+        | (head.rsv[0] as u8) << 6
+        | (head.rsv[1] as u8) << 5
+        | (head.rsv[2] as u8) << 4
+        | op;
+
+    let mut len = match payload_len {
+        PayloadLen::Short(len) if len > 125 => return Err(FrameError::InvalidLengthForm),
+        PayloadLen::Short(len) => {
+            buf[1] = len;
+            2
+        }
+        PayloadLen::Extended16(len) => {
+            buf[1] = 126;
+            buf[2..4].copy_from_slice(&len.to_be_bytes());
+            4
+        }
+        PayloadLen::Extended64(len) => {
+            buf[1] = 127;
+            buf[2..10].copy_from_slice(&len.to_be_bytes());
+            10
+        }
+    };
+
+    if let Some(mask) = mask {
+        buf[1] |= 0x80;
+        buf[len..len + 4].copy_from_slice(&mask);
+        len += 4;
+    }
+
+    paranoid_assert!(len <= MAX_HEAD_LEN);
+    Ok((buf, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, Status};
+
+    #[test]
+    fn masked_control_round_trip() {
+        let key = [1, 2, 3, 4];
+        let payload = [0xAA; 125];
+        let (buf, len) = encode_control(Opcode::Ping, &payload, Some(key)).unwrap();
+        assert_eq!(MAX_CONTROL_FRAME_LEN, len);
+
+        let mut f = Frame::empty();
+        assert_eq!(Status::Complete(6), f.decode(&buf[..len]));
+        assert_eq!(Some(key), f.mask);
+        assert_eq!(Some(125), f.payload_len);
+        assert_eq!(0xAA ^ 2, buf[7]);
+    }
+
+    #[test]
+    fn rejects_invalid_control_frames() {
+        assert_eq!(
+            Err(FrameError::ControlFrameTooLong),
+            encode_control(Opcode::Close, &[0; 126], None)
+        );
+        assert_eq!(
+            Err(FrameError::InvalidOpcode),
+            encode_control(Opcode::Text, &[], None)
+        );
+    }
+
+    #[test]
+    fn templates_match_encoder() {
+        let encoded = |op, payload: &[u8]| {
+            let (buf, len) = encode_control(op, payload, None).unwrap();
+            buf[..len].to_vec()
+        };
+        assert_eq!(&EMPTY_PING[..], &encoded(Opcode::Ping, &[])[..]);
+        assert_eq!(&EMPTY_PONG[..], &encoded(Opcode::Pong, &[])[..]);
+        assert_eq!(
+            &CLOSE_NORMAL[..],
+            &encoded(Opcode::Close, &1000u16.to_be_bytes())[..]
+        );
+    }
+
+    #[test]
+    fn uninit_length_forms() {
+        let head = Head {
+            op: Opcode::Binary,
+            finished: false,
+            rsv: [true, false, false],
+        };
+        let payload = [7; 300];
+        let mut out = [MaybeUninit::uninit(); 304];
+        assert_eq!(Ok(304), encode_uninit(&head, &payload, None, &mut out));
+
+        let out = out.map(|b| unsafe { b.assume_init() });
+        let mut f = Frame::empty();
+        assert_eq!(Status::Complete(4), f.decode(&out));
+        assert_eq!(Some(&head), f.head.as_ref());
+        assert_eq!(Some(300), f.payload_len);
+
+        let mut out = [MaybeUninit::uninit(); 303];
+        assert_eq!(
+            Err(FrameError::BufferTooSmall),
+            encode_uninit(&head, &payload, None, &mut out)
+        );
+
+        let key = [1, 2, 3, 4];
+        let mut out = [MaybeUninit::uninit(); 308];
+        assert_eq!(Ok(308), encode_uninit(&head, &payload, Some(key), &mut out));
+        let mut masked = [0; 308];
+        assert_eq!(Ok(308), encode_masked(&head, &payload, key, &mut masked));
+        assert_eq!(masked, out.map(|b| unsafe { b.assume_init() }));
+    }
+
+    #[test]
+    fn heads_use_minimal_forms() {
+        let head = Head {
+            op: Opcode::Text,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut out = [0; 14];
+        for &(payload_len, head_len) in &[(125, 2), (126, 4), (0xFFFF, 4), (0x10000, 10)] {
+            assert_eq!(
+                Ok(head_len),
+                encode_head(&head, payload_len, None, &mut out)
+            );
+            let mut f = Frame::empty();
+            assert_eq!(Status::Complete(head_len), f.decode_strict(&out));
+            assert_eq!(Some(payload_len), f.payload_len);
+        }
+
+        assert_eq!(
+            Err(FrameError::BufferTooSmall),
+            encode_head(&head, 0, Some([1, 2, 3, 4]), &mut out[..5])
+        );
+
+        let max = (1 << 63) - 1;
+        assert_eq!(Ok(10), encode_head(&head, max, None, &mut out));
+        let mut f = Frame::empty();
+        assert_eq!(Status::Complete(10), f.decode(&out));
+        assert_eq!(Some(max), f.payload_len);
+        assert_eq!(
+            Err(FrameError::LengthMsb),
+            encode_head(&head, max + 1, None, &mut out)
+        );
+    }
+
+    #[test]
+    fn masked_encodings_agree() {
+        let head = Head {
+            op: Opcode::Binary,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let key = [0x10, 0x20, 0x30, 0x40];
+        let mut payload = [0; 200];
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut out = [0; 208];
+        assert_eq!(Ok(208), encode_masked(&head, &payload, key, &mut out));
+        assert_eq!(
+            Err(FrameError::BufferTooSmall),
+            encode_masked(&head, &payload, key, &mut [0; 207])
+        );
+
+        let mut in_place = payload;
+        let mut head_out = [0; MAX_HEAD_LEN];
+        let len = encode_masked_in_place(&head, &mut in_place, key, &mut head_out).unwrap();
+        assert_eq!(&out[..len], &head_out[..len]);
+        assert_eq!(&out[len..], &in_place[..]);
+    }
+
+    #[test]
+    fn vectored_matches_contiguous() {
+        let head = Head {
+            op: Opcode::Text,
+            finished: false,
+            rsv: [false; 3],
+        };
+        let key = [5, 6, 7, 8];
+        let mut out = [0; 11];
+        assert_eq!(Ok(11), encode_masked(&head, b"hello", key, &mut out));
+
+        let mut payload = *b"hello";
+        let frame = encode_vectored_masked(&head, &mut payload, key).unwrap();
+        assert_eq!(&out[..6], frame.header.as_bytes());
+        assert_eq!(&out[6..], frame.payload);
+
+        let frame = encode_vectored(&head, b"hello").unwrap();
+        assert_eq!([0x01, 0x05], frame.header.as_bytes());
+    }
+
+    #[test]
+    fn pongs_echo_masked_pings() {
+        let mut ping = [0; 7];
+        assert_eq!(Ok(7), encode_ping(b"a", Some([9; 4]), &mut ping));
+        assert_eq!(
+            Err(FrameError::ControlFrameTooLong),
+            encode_ping(&[0; 126], None, &mut [0; 256])
+        );
+
+        let mut f = Frame::empty();
+        assert!(f.decode(&ping).is_complete());
+        let data = [f.payload(&ping).unwrap()[0] ^ 9];
+
+        let mut pong = [0; 2];
+        assert_eq!(
+            Err(FrameError::BufferTooSmall),
+            encode_pong_for(&data, None, &mut pong)
+        );
+        let mut pong = [0; 3];
+        assert_eq!(Ok(3), encode_pong_for(&data, None, &mut pong));
+        assert_eq!([0x8A, 0x01, b'a'], pong);
+    }
+
+    #[test]
+    fn reserved_opcodes_round_trip() {
+        let head = Head {
+            op: Opcode::Reserved(0x3),
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut out = [MaybeUninit::uninit(); 2];
+        assert_eq!(Ok(2), encode_uninit(&head, &[], None, &mut out));
+
+        let out = out.map(|b| unsafe { b.assume_init() });
+        let mut f = Frame::empty();
+        assert!(f.decode(&out).is_complete());
+        assert_eq!(Some(&head), f.head.as_ref());
+
+        let head = Head {
+            op: Opcode::Reserved(0x10),
+            ..head
+        };
+        assert_eq!(
+            Err(FrameError::InvalidOpcode),
+            encode_uninit(&head, &[], None, &mut [MaybeUninit::uninit(); 2])
+        );
+    }
+}
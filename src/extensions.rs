@@ -0,0 +1,98 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Per-connection state kept by type, for extensions and middleware.
+///
+/// Each type holds at most one value, so a deflate context or a rate limiter
+/// bucket can live on the connection it belongs to instead of in a map keyed
+/// by connection. Both `Connection` and `WebSocketStream` carry one.
+///
+/// # Example
+/// ```
+/// use ws_frame::Extensions;
+///
+/// struct Budget(u32);
+///
+/// let mut extensions = Extensions::new();
+/// extensions.insert(Budget(10));
+/// extensions.get_mut::<Budget>().unwrap().0 -= 1;
+/// assert_eq!(9, extensions.get::<Budget>().unwrap().0);
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Creates an empty `Extensions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value`, returning the value of the same type it replaces.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// Returns the value of type `T`, if one is stored.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Returns a mutable reference to the value of type `T`, if one is
+    /// stored.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Takes out the value of type `T`, if one is stored.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Returns the amount of values stored.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if nothing is stored.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_one_value_per_type() {
+        let mut extensions = Extensions::new();
+        assert_eq!(None, extensions.insert(1u32));
+        assert_eq!(None, extensions.insert("state"));
+        assert_eq!(Some(1), extensions.insert(2u32));
+        assert_eq!(2, extensions.len());
+
+        assert_eq!(Some(&2), extensions.get::<u32>());
+        assert_eq!(None, extensions.get::<u64>());
+        assert_eq!(Some("state"), extensions.remove::<&str>());
+        assert_eq!(1, extensions.len());
+    }
+}
@@ -0,0 +1,128 @@
+use core::convert::TryFrom;
+
+use fastwebsockets::{OpCode, Payload};
+
+use crate::{Frame, FrameError, Head, Opcode, Status};
+
+impl From<OpCode> for Opcode {
+    fn from(opcode: OpCode) -> Opcode {
+        Opcode::from_u8(opcode as u8)
+    }
+}
+
+impl TryFrom<Opcode> for OpCode {
+    type Error = FrameError;
+
+    /// Fails with [`FrameError::InvalidOpcode`] for reserved opcodes, which
+    /// fastwebsockets can't represent.
+    fn try_from(opcode: Opcode) -> Result<OpCode, FrameError> {
+        match opcode {
+            Opcode::Continue => Ok(OpCode::Continuation),
+            Opcode::Text => Ok(OpCode::Text),
+            Opcode::Binary => Ok(OpCode::Binary),
+            Opcode::Close => Ok(OpCode::Close),
+            Opcode::Ping => Ok(OpCode::Ping),
+            Opcode::Pong => Ok(OpCode::Pong),
+            Opcode::Reserved(_) => Err(FrameError::InvalidOpcode),
+        }
+    }
+}
+
+impl<'f> From<&mut fastwebsockets::Frame<'f>> for Frame {
+    /// Decodes the header fastwebsockets would write for `frame`.
+    ///
+    /// The header isn't validated, as fastwebsockets can build frames the
+    /// decoder rejects, such as a Ping longer than 125 bytes. This needs a mutable reference because fastwebsockets only exposes a
+    /// frame's mask key through `fmt_head`.
+    fn from(frame: &mut fastwebsockets::Frame<'f>) -> Frame {
+        let mut head = [0; 14];
+        let len = frame.fmt_head(&mut head);
+
+        let mut f = Frame::empty();
+        match f.decode_unchecked(&head[..len]) {
+            Status::Complete(_) => f,
+            status => unreachable!("fastwebsockets wrote a partial head: {:?}", status),
+        }
+    }
+}
+
+impl Head {
+    /// Builds a fastwebsockets frame with this header, the mask key `mask`
+    /// and the unmasked `payload`, which fastwebsockets masks as it writes
+    /// the frame.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::ReservedBits`] if an RSV bit is set and
+    /// [`FrameError::InvalidOpcode`] for a reserved opcode, neither of which
+    /// fastwebsockets frames can carry.
+    pub fn to_fastwebsockets<'f>(
+        &self,
+        mask: Option<[u8; 4]>,
+        payload: &'f [u8],
+    ) -> Result<fastwebsockets::Frame<'f>, FrameError> {
+        if self.rsv != [false; 3] {
+            return Err(FrameError::ReservedBits);
+        }
+        let opcode = OpCode::try_from(self.op)?;
+        Ok(fastwebsockets::Frame::new(
+            self.finished,
+            opcode,
+            mask,
+            Payload::Borrowed(payload),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_from_fastwebsockets_frame() {
+        let key = [1, 2, 3, 4];
+        let payload = [0; 300];
+        let mut frame = fastwebsockets::Frame::new(
+            false,
+            OpCode::Binary,
+            Some(key),
+            Payload::Borrowed(&payload),
+        );
+
+        let f = Frame::from(&mut frame);
+        let head = f.head.unwrap();
+        assert_eq!(Opcode::Binary, head.op);
+        assert!(!head.finished);
+        assert_eq!(Some(key), f.mask);
+        assert_eq!(Some(300), f.payload_len);
+
+        assert_eq!(Ok(OpCode::Binary), OpCode::try_from(head.op));
+        assert!(OpCode::try_from(Opcode::Reserved(3)).is_err());
+    }
+
+    #[test]
+    fn converts_back_to_fastwebsockets() {
+        let ping = Head {
+            op: Opcode::Ping,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let payload = [0; 200];
+        let mut frame = ping
+            .to_fastwebsockets(Some([1, 2, 3, 4]), &payload)
+            .unwrap();
+        assert_eq!(OpCode::Ping, frame.opcode);
+
+        let f = Frame::from(&mut frame);
+        assert_eq!(Some(&ping), f.head.as_ref());
+        assert_eq!((Some([1, 2, 3, 4]), Some(200)), (f.mask, f.payload_len));
+
+        let rsv = Head {
+            rsv: [true, false, false],
+            ..ping
+        };
+        assert_eq!(
+            Some(FrameError::ReservedBits),
+            rsv.to_fastwebsockets(None, &payload).err()
+        );
+    }
+}
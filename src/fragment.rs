@@ -0,0 +1,110 @@
+use core::cmp;
+use core::ops::Range;
+
+use crate::{Head, Opcode};
+
+/// Splits a message into fragments of at most a given size.
+///
+/// Yields the header and the range of the message carried by each fragment:
+/// the first with the message's opcode, the rest as continuations, and only
+/// the last one finished. An empty message is sent as a single empty frame.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Fragmenter, Opcode};
+///
+/// let message = b"hello";
+/// for (head, range) in Fragmenter::new(Opcode::Text, message.len(), 2) {
+///     let payload = &message[range];
+///     match payload {
+///         b"he" => assert_eq!((Opcode::Text, false), (head.op, head.finished)),
+///         b"ll" => assert_eq!((Opcode::Continue, false), (head.op, head.finished)),
+///         _ => assert_eq!((Opcode::Continue, true), (head.op, head.finished)),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fragmenter {
+    op: Opcode,
+    len: usize,
+    max_fragment: usize,
+    pos: usize,
+    done: bool,
+}
+
+impl Fragmenter {
+    /// Creates a `Fragmenter` for a message of `len` bytes.
+    ///
+    /// # Panics
+    /// Panics if `op` isn't Text or Binary, or if `max_fragment` is 0.
+    pub fn new(op: Opcode, len: usize, max_fragment: usize) -> Self {
+        assert!(
+            op == Opcode::Text || op == Opcode::Binary,
+            "messages must be Text or Binary"
+        );
+        assert!(max_fragment > 0, "fragments must hold at least one byte");
+        Self {
+            op,
+            len,
+            max_fragment,
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Fragmenter {
+    type Item = (Head, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let start = self.pos;
+        let end = start + cmp::min(self.max_fragment, self.len - start);
+        self.pos = end;
+        self.done = end == self.len;
+
+        let head = Head {
+            op: if start == 0 {
+                self.op
+            } else {
+                Opcode::Continue
+            },
+            finished: self.done,
+            rsv: [false; 3],
+        };
+        Some((head, start..end))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = if self.done {
+            0
+        } else {
+            cmp::max(1, (self.len - self.pos).div_ceil(self.max_fragment))
+        };
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Fragmenter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_the_whole_message() {
+        let mut fragments = Fragmenter::new(Opcode::Binary, 0, 4);
+        let (head, range) = fragments.next().unwrap();
+        assert!(head.finished && range.is_empty());
+        assert_eq!(None, fragments.next());
+
+        let fragmenter = Fragmenter::new(Opcode::Binary, 8, 4);
+        assert_eq!(2, fragmenter.len());
+        assert!(fragmenter
+            .map(|(_, range)| range)
+            .eq([0..4, 4..8].iter().cloned()));
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{Frame, FrameError, Head, Status};
+
+/// An iterator over the complete frames packed into one buffer.
+///
+/// Each item is a frame's header, mask key and (still masked) payload.
+/// Iteration stops at the first trailing partial frame, or after yielding an
+/// error; [`consumed`](FrameIter::consumed) then tells how many bytes the
+/// complete frames took up.
+///
+/// # Example
+/// ```
+/// use ws_frame::{FrameIter, Opcode};
+///
+/// // A Ping, a text frame and the start of another text frame.
+/// let buf = [0x89, 0x00, 0x81, 0x02, b'h', b'i', 0x81, 0x05];
+/// let mut iter = FrameIter::new(&buf);
+///
+/// let ops: Vec<_> = iter.by_ref().map(|f| f.unwrap().0.op).collect();
+/// assert_eq!(vec![Opcode::Ping, Opcode::Text], ops);
+/// assert_eq!(6, iter.consumed());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameIter<'a> {
+    buf: &'a [u8],
+    consumed: usize,
+    done: bool,
+}
+
+impl<'a> FrameIter<'a> {
+    /// Creates an iterator over the frames at the start of `buf`.
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            consumed: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the amount of bytes taken up by the frames yielded so far.
+    ///
+    /// Any bytes after that are the start of a partial frame, and must be
+    /// passed again at the start of the next buffer.
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+}
+
+impl<'a> Iterator for FrameIter<'a> {
+    type Item = Result<(Head, Option<[u8; 4]>, &'a [u8]), FrameError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let buf = &self.buf[self.consumed..];
+        let mut frame = Frame::empty();
+        let head_len = match frame.decode(buf) {
+            Status::Complete(len) => len,
+            Status::Partial(_) => {
+                self.done = true;
+                return None;
+            }
+            Status::Error(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+
+        match frame.payload(buf) {
+            Some(payload) => {
+                self.consumed += head_len + payload.len();
+                Some(Ok((frame.head?, frame.mask, payload)))
+            }
+            None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+/// A complete frame borrowed from a buffer, as returned by [`decode_all`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodedFrame<'a> {
+    /// The head section of the frame.
+    pub head: Head,
+    /// The mask key to apply over the payload, if any.
+    pub mask: Option<[u8; 4]>,
+    /// The payload, still masked.
+    pub payload: &'a [u8],
+}
+
+/// Decodes every complete frame in `buf`.
+///
+/// Returns the frames along with the amount of bytes they took up. Any bytes
+/// after that are the start of a partial frame.
+///
+/// # Example
+/// ```
+/// use ws_frame::{decode_all, Opcode};
+///
+/// let buf = [0x89, 0x00, 0x81, 0x02, b'h', b'i', 0x81, 0x05];
+/// let (frames, used) = decode_all(&buf).unwrap();
+///
+/// assert_eq!(2, frames.len());
+/// assert_eq!((Opcode::Text, &b"hi"[..]), (frames[1].head.op, frames[1].payload));
+/// assert_eq!(6, used);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode_all(buf: &[u8]) -> Result<(Vec<DecodedFrame<'_>>, usize), FrameError> {
+    let mut iter = FrameIter::new(buf);
+    let frames = iter
+        .by_ref()
+        .map(|frame| {
+            frame.map(|(head, mask, payload)| DecodedFrame {
+                head,
+                mask,
+                payload,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((frames, iter.consumed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_after_errors() {
+        // A masked Pong, then a fragmented Ping.
+        let buf = [0x8A, 0x81, 1, 2, 3, 4, 0xFF, 0x09, 0x00, 0x89, 0x00];
+        let mut iter = FrameIter::new(&buf);
+
+        let (head, mask, payload) = iter.next().unwrap().unwrap();
+        assert!(head.finished);
+        assert_eq!((Some([1, 2, 3, 4]), &[0xFF][..]), (mask, payload));
+
+        assert_eq!(Some(Err(FrameError::FragmentedControl)), iter.next());
+        assert_eq!(None, iter.next());
+        assert_eq!(7, iter.consumed());
+    }
+}
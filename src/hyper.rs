@@ -0,0 +1,29 @@
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+
+use crate::{Role, WebSocketStream};
+
+impl WebSocketStream<TokioIo<Upgraded>> {
+    /// Wraps a connection upgraded by hyper after a 101 response.
+    ///
+    /// The handshake is left to the caller, who checks the request and sends
+    /// the response with [`handshake`](crate::handshake), then awaits
+    /// `hyper::upgrade::on` to get the stream. Servers pass [`Role::Server`]
+    /// and clients [`Role::Client`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use hyper::{body::Incoming, Request};
+    /// use ws_frame::{Role, WebSocketStream};
+    ///
+    /// async fn upgrade(request: Request<Incoming>) -> hyper::Result<()> {
+    ///     // The 101 response has been sent by the service.
+    ///     let upgraded = hyper::upgrade::on(request).await?;
+    ///     let ws = WebSocketStream::from_upgraded(upgraded, Role::Server);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_upgraded(upgraded: Upgraded, role: Role) -> Self {
+        WebSocketStream::from_raw(TokioIo::new(upgraded), role)
+    }
+}
@@ -0,0 +1,51 @@
+use core::iter::Copied;
+
+use crate::Bytes;
+
+/// A source of bytes that frame headers can be decoded from, with
+/// [`Frame::decode_from`](crate::Frame::decode_from).
+///
+/// It's implemented for the [`Bytes`] cursor, and for iterators over borrowed
+/// bytes such as `a.iter().chain(b).copied()`, so headers can be decoded from
+/// chained or otherwise non-contiguous buffers.
+pub trait InputSource {
+    /// Reads the next byte, or returns `None` if there are no more.
+    fn next_byte(&mut self) -> Option<u8>;
+
+    /// Reads as many bytes as fit in `out`, returning how many were read.
+    ///
+    /// The default implementation calls [`next_byte`](InputSource::next_byte)
+    /// for each one.
+    fn read_into(&mut self, out: &mut [u8]) -> usize {
+        for (i, b) in out.iter_mut().enumerate() {
+            match self.next_byte() {
+                Some(next) => *b = next,
+                None => return i,
+            }
+        }
+        out.len()
+    }
+}
+
+impl InputSource for Bytes<'_> {
+    fn next_byte(&mut self) -> Option<u8> {
+        self.next()
+    }
+
+    fn read_into(&mut self, out: &mut [u8]) -> usize {
+        let n = out.len().min(self.remaining());
+        if let Some(slice) = self.slice_to(n) {
+            out[..n].copy_from_slice(slice);
+        }
+        n
+    }
+}
+
+impl<'a, I> InputSource for Copied<I>
+where
+    I: Iterator<Item = &'a u8>,
+{
+    fn next_byte(&mut self) -> Option<u8> {
+        self.next()
+    }
+}
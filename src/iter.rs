@@ -1,19 +1,89 @@
+use byteorder::{BigEndian, ByteOrder};
+
+/// A cursor over a byte slice.
+///
+/// This is the cursor used internally to decode frames, exposed so that
+/// related protocol layers can reuse it.
+///
+/// # Example
+/// ```
+/// use ws_frame::Bytes;
+///
+/// let mut bytes = Bytes::new(&[0x81, 0x7E, 0x01, 0x00]);
+/// let start = bytes.checkpoint();
+///
+/// assert_eq!(Some(0x81), bytes.next());
+/// assert_eq!(Some(0x7E), bytes.peek());
+/// assert_eq!(None, bytes.read_u64_be());
+///
+/// bytes.rollback(start);
+/// assert_eq!(Some(0x817E), bytes.read_u16_be());
+/// assert_eq!(2, bytes.remaining());
+/// ```
+#[derive(Debug, Clone)]
 pub struct Bytes<'a> {
     slice: &'a [u8],
     pos: usize,
 }
 
+/// A saved position of a [`Bytes`] cursor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Checkpoint(usize);
+
 impl<'a> Bytes<'a> {
+    /// Creates a cursor at the start of `slice`.
     pub fn new(slice: &'a [u8]) -> Self {
         Self { slice, pos: 0 }
     }
+
+    /// Returns the amount of bytes read so far.
     pub fn pos(&self) -> usize {
         self.pos
     }
+
+    /// Returns the amount of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.slice.len() - self.pos
+    }
+
+    /// Returns the next byte without reading it.
+    pub fn peek(&self) -> Option<u8> {
+        self.slice.get(self.pos).copied()
+    }
+
+    /// Reads the next `end` bytes, or returns `None` without reading anything
+    /// if fewer are left.
     pub fn slice_to(&mut self, end: usize) -> Option<&'a [u8]> {
         let start = self.pos;
+        let slice = self.slice.get(start..start.checked_add(end)?)?;
         self.pos += end;
-        self.slice.get(start..self.pos)
+        Some(slice)
+    }
+
+    /// Reads a big-endian `u16`.
+    pub fn read_u16_be(&mut self) -> Option<u16> {
+        self.slice_to(2).map(BigEndian::read_u16)
+    }
+
+    /// Reads a big-endian `u64`.
+    pub fn read_u64_be(&mut self) -> Option<u64> {
+        self.slice_to(8).map(BigEndian::read_u64)
+    }
+
+    /// Saves the current position so it can be restored with
+    /// [`rollback`](Bytes::rollback).
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.pos)
+    }
+
+    /// Restores a position saved with [`checkpoint`](Bytes::checkpoint).
+    ///
+    /// # Panics
+    /// Panics if the checkpoint is past the end of the slice, which can only
+    /// happen if it came from a different cursor.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        assert!(checkpoint.0 <= self.slice.len());
+        self.pos = checkpoint.0;
     }
 }
 
@@ -0,0 +1,74 @@
+use serde_json::{json, Value};
+
+use crate::{Frame, Opcode};
+
+impl Opcode {
+    fn json_name(self) -> &'static str {
+        match self {
+            Opcode::Continue => "continue",
+            Opcode::Text => "text",
+            Opcode::Binary => "binary",
+            Opcode::Close => "close",
+            Opcode::Ping => "ping",
+            Opcode::Pong => "pong",
+            Opcode::Reserved(_) => "reserved",
+        }
+    }
+}
+
+impl Frame {
+    /// Serializes this frame header for log pipelines.
+    ///
+    /// The schema is stable: every key is always present, with `null` for
+    /// parts that weren't decoded. `close_code` is `null` unless the frame is
+    /// a Close frame and `payload` holds its code.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Frame;
+    ///
+    /// let mut f = Frame::empty();
+    /// f.decode(&[0x88, 0x02, 0x03, 0xE8]);
+    ///
+    /// assert_eq!(
+    ///     r#"{"close_code":1000,"fin":true,"len":2,"masked":false,"opcode":"close","rsv":[false,false,false]}"#,
+    ///     f.to_json(&[0x03, 0xE8]).to_string()
+    /// );
+    /// ```
+    pub fn to_json(&self, payload: &[u8]) -> Value {
+        let head = self.head.as_ref();
+        let close_code = match (head.map(|head| head.op), payload) {
+            (Some(Opcode::Close), [a, b, ..]) => {
+                let mask = self.mask.unwrap_or([0; 4]);
+                Some(u16::from_be_bytes([a ^ mask[0], b ^ mask[1]]))
+            }
+            _ => None,
+        };
+
+        json!({
+            "opcode": head.map(|head| head.op.json_name()),
+            "fin": head.map(|head| head.finished),
+            "rsv": head.map(|head| head.rsv),
+            "len": self.payload_len,
+            "masked": self.payload_len.map(|_| self.mask.is_some()),
+            "close_code": close_code,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_frame_has_every_key() {
+        let mut f = Frame::empty();
+        f.decode(&[0x81]);
+
+        let json = f.to_json(&[]);
+        assert_eq!(json["opcode"], "text");
+        assert_eq!(json["len"], Value::Null);
+        assert_eq!(json["masked"], Value::Null);
+        assert_eq!(6, json.as_object().unwrap().len());
+    }
+}
@@ -22,25 +22,229 @@
 #[cfg(feature = "std")]
 extern crate std as core;
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use byteorder::{BigEndian, ByteOrder};
+use core::convert::TryFrom;
+use core::ops::Deref;
+
+/// Checks an internal invariant when the `paranoid` feature is enabled, and
+/// compiles to nothing otherwise.
+macro_rules! paranoid_assert {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "paranoid")]
+        {
+            assert!($($arg)*);
+        }
+    };
+}
 
 mod iter;
-use iter::Bytes;
+pub use iter::{Bytes, Checkpoint};
+
+mod input;
+pub use input::InputSource;
+
+mod policy;
+pub use policy::{AcceptAll, ControlChecks, Strict, ValidationPolicy};
+
+mod role;
+pub use role::Role;
+
+mod config;
+pub use config::DecoderConfig;
+
+mod lint;
+pub use lint::Anomaly;
+
+pub mod conformance;
+
+mod utf8;
+pub use utf8::{Utf8Error, Utf8Validator};
+
+mod close;
+pub use close::{
+    encode_close, encode_close_reply, parse_close, truncate_reason, CloseCode, MAX_REASON_LEN,
+};
+
+mod message;
+pub use message::Message;
+#[cfg(feature = "alloc")]
+pub use message::OwnedMessage;
+
+#[cfg(feature = "alloc")]
+mod assembler;
+#[cfg(feature = "alloc")]
+pub use assembler::MessageAssembler;
+
+mod scan;
+pub use scan::Scanner;
+
+mod drain;
+pub use drain::{Drain, Drained};
+
+mod decoder;
+pub use decoder::{Decoder, Event, Segment};
+
+mod count;
+pub use count::{count_frames, FrameCount};
+
+mod spans;
+pub use spans::Spans;
+
+mod frame_iter;
+pub use frame_iter::FrameIter;
+#[cfg(feature = "alloc")]
+pub use frame_iter::{decode_all, DecodedFrame};
+
+mod seq;
+pub use seq::{Sequenced, Sequencer};
+
+mod encode;
+pub use encode::{
+    encode_control, encode_head, encode_head_as, encode_masked, encode_masked_in_place,
+    encode_masked_with, encode_ping, encode_pong_for, encode_uninit, encode_vectored,
+    encode_vectored_masked, HeaderBuf, VectoredFrame, CLOSE_NORMAL, EMPTY_PING, EMPTY_PONG,
+    MAX_CONTROL_FRAME_LEN,
+};
+
+mod fragment;
+pub use fragment::Fragmenter;
+
+mod builder;
+pub use builder::FrameBuilder;
+
+mod combinator;
+pub use combinator::{decode, parse, DecodeError};
+
+mod mask;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "rayon")]
+pub use mask::apply_mask_parallel;
+#[cfg(feature = "getrandom")]
+pub use mask::OsMaskKeys;
+#[cfg(feature = "rand_core")]
+pub use mask::RngMaskKeys;
+pub use mask::{apply_mask, apply_mask_into, apply_mask_offset, MaskKeyGenerator, UnmaskedBytes};
+
+#[cfg(feature = "std")]
+mod buf;
+#[cfg(feature = "std")]
+pub use buf::FrameBuf;
+
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(feature = "std")]
+pub use blocking::{read_head, CoalescingWriter, FrameWriter};
+
+#[cfg(feature = "handshake")]
+pub mod handshake;
+
+#[cfg(any(feature = "net", feature = "tokio"))]
+mod session;
+
+#[cfg(any(feature = "net", feature = "tokio"))]
+mod extensions;
+#[cfg(any(feature = "net", feature = "tokio"))]
+pub use extensions::Extensions;
+
+#[cfg(any(feature = "net", feature = "tokio"))]
+mod quirks;
+#[cfg(any(feature = "net", feature = "tokio"))]
+pub use quirks::Quirks;
+
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "net")]
+pub use net::{accept, accept_with, client, connect, connect_via, Connection};
+
+#[cfg(feature = "rustls")]
+mod tls;
+#[cfg(feature = "rustls")]
+pub use tls::{accept_tls, connect_tls, TlsClientStream, TlsServerStream};
+
+#[cfg(feature = "tokio")]
+mod queue;
+#[cfg(feature = "tokio")]
+pub use queue::Overflow;
+
+#[cfg(feature = "tokio")]
+mod ws_stream;
+#[cfg(feature = "tokio")]
+pub use ws_stream::{Messages, WebSocketStream};
+
+#[cfg(feature = "hyper")]
+mod hyper;
+
+#[cfg(feature = "tokio")]
+mod split;
+#[cfg(feature = "tokio")]
+pub use split::{RecvHalf, SendHalf};
+
+#[cfg(feature = "futures-io")]
+mod stream;
+#[cfg(feature = "futures-io")]
+pub use stream::FrameStream;
+
+#[cfg(feature = "futures-io")]
+mod writer;
+#[cfg(feature = "futures-io")]
+pub use writer::MessageWriter;
+
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "fastwebsockets")]
+mod fastwebsockets;
+
+#[cfg(feature = "axum")]
+mod axum;
+
+#[cfg(feature = "metrics")]
+mod metrics;
+
+#[cfg(kani)]
+mod proofs;
+
+mod time;
+#[cfg(feature = "std")]
+pub use time::SystemClock;
+pub use time::{Clock, Instant, Millis};
 
 macro_rules! unwrap {
-    ($e:expr) => {
+    ($e:expr, $buf:expr) => {
         match $e {
             Some(t) => t,
-            None => return Status::Partial,
+            None => return Status::Partial(header_needed($buf)),
+        }
+    };
+}
+
+macro_rules! check {
+    ($e:expr) => {
+        if let Err(e) = $e {
+            return Status::Error(e);
         }
     };
 }
 
-/// The result of a successful decode pass.
+/// The result of a decode pass.
 ///
 /// `Complete` is used when the buffer
 /// contained the complete value. `Partial` is used when decoding did not reach
-/// the end of the expected value, but no invalid data was found.
+/// the end of the expected value, but no invalid data was found. `Error` is
+/// used when the data was rejected.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Frame, Status};
+///
+/// // A masked frame needs 4 more bytes for its mask key.
+/// let mut f = Frame::empty();
+/// assert_eq!(Status::Partial(4), f.decode(&[0x81, 0x85]));
+/// ```
 #[derive(Debug, PartialEq)]
 pub enum Status {
     /// The completed result.
@@ -48,7 +252,12 @@ pub enum Status {
     /// Contains the amount of bytes decoded.
     Complete(usize),
     /// A partial result.
-    Partial,
+    ///
+    /// Contains the minimum amount of further bytes needed to make progress.
+    /// That's the rest of the header once its first two bytes are known.
+    Partial(usize),
+    /// The frame was rejected.
+    Error(FrameError),
 }
 
 impl Status {
@@ -57,7 +266,7 @@ impl Status {
     pub fn is_complete(&self) -> bool {
         match *self {
             Status::Complete(..) => true,
-            Status::Partial => false,
+            Status::Partial(..) | Status::Error(..) => false,
         }
     }
 
@@ -65,18 +274,95 @@ impl Status {
     #[inline]
     pub fn is_partial(&self) -> bool {
         match *self {
-            Status::Complete(..) => false,
-            Status::Partial => true,
+            Status::Partial(..) => true,
+            Status::Complete(..) | Status::Error(..) => false,
+        }
+    }
+
+    /// Convenience method to check if status is an error.
+    #[inline]
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Status::Error(..) => true,
+            Status::Complete(..) | Status::Partial(..) => false,
         }
     }
 
     /// Convenience method to unwrap a Complete value. Panics if the status is
-    /// partial.
+    /// partial or an error.
     #[inline]
     pub fn unwrap(self) -> usize {
         match self {
             Status::Complete(len) => len,
-            Status::Partial => panic!("Tried to unwrap Status::Partial"),
+            Status::Partial(_) => panic!("Tried to unwrap Status::Partial"),
+            Status::Error(e) => panic!("Tried to unwrap Status::Error({:?})", e),
+        }
+    }
+}
+
+/// An error encountered while decoding or encoding a frame.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FrameError {
+    /// The frame was rejected by a [`ValidationPolicy`], with a short reason.
+    Rejected(&'static str),
+    /// The opcode can't be used here.
+    InvalidOpcode,
+    /// A control frame payload was longer than 125 bytes.
+    ControlFrameTooLong,
+    /// The output buffer was too small to hold the encoded frame.
+    BufferTooSmall,
+    /// The payload is longer than this target can address.
+    PayloadTooLarge,
+    /// RSV bits were set without an extension defining them.
+    ReservedBits,
+    /// A control frame didn't have FIN set.
+    FragmentedControl,
+    /// The payload length didn't use the shortest encoding.
+    NonMinimalLength,
+    /// A 64-bit payload length had its most significant bit set.
+    LengthMsb,
+    /// A server received an unmasked frame.
+    MissingMask,
+    /// A client received a masked frame.
+    UnexpectedMask,
+    /// The payload is longer than the configured maximum.
+    PayloadOverLimit,
+    /// The payload length used a form longer than the configured maximum.
+    LengthFormRejected,
+    /// A close code that must not be sent on the wire.
+    InvalidCloseCode,
+    /// A payload length was too long for the form it was to be encoded in.
+    InvalidLengthForm,
+    /// A text payload wasn't valid UTF-8.
+    InvalidUtf8,
+}
+
+impl FrameError {
+    /// Returns the close code to fail the connection with.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::FrameError;
+    ///
+    /// assert_eq!(1002, FrameError::ReservedBits.close_code());
+    /// ```
+    pub fn close_code(&self) -> u16 {
+        match self {
+            FrameError::Rejected(_) => 1008,
+            FrameError::BufferTooSmall | FrameError::InvalidLengthForm => 1011,
+            FrameError::InvalidUtf8 => 1007,
+            FrameError::PayloadTooLarge
+            | FrameError::PayloadOverLimit
+            | FrameError::LengthFormRejected => 1009,
+            FrameError::InvalidOpcode
+            | FrameError::ControlFrameTooLong
+            | FrameError::ReservedBits
+            | FrameError::FragmentedControl
+            | FrameError::NonMinimalLength
+            | FrameError::LengthMsb
+            | FrameError::MissingMask
+            | FrameError::UnexpectedMask
+            | FrameError::InvalidCloseCode => 1002,
         }
     }
 }
@@ -89,30 +375,171 @@ pub enum Opcode {
     Close,
     Ping,
     Pong,
-    Reserved,
+    /// An opcode reserved for future use, holding its raw value.
+    Reserved(u8),
 }
 
-impl From<u8> for Opcode {
-    fn from(opcode: u8) -> Opcode {
-        match opcode {
+impl Opcode {
+    /// Converts a raw value into an opcode, keeping unknown values as
+    /// [`Opcode::Reserved`].
+    ///
+    /// Only the low 4 bits are used, as in the first byte of a frame. This is
+    /// also what `From<u8>` does; use [`try_from_u8`](Opcode::try_from_u8)
+    /// instead to reject reserved opcodes.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Opcode;
+    ///
+    /// assert_eq!(Opcode::Ping, Opcode::from_u8(0x9));
+    /// assert_eq!(Opcode::Reserved(0xB), Opcode::from_u8(0xB));
+    /// assert_eq!(Opcode::Text, Opcode::from(0x81));
+    /// ```
+    pub fn from_u8(opcode: u8) -> Opcode {
+        match opcode & 0xF {
             0 => Opcode::Continue,
             1 => Opcode::Text,
             2 => Opcode::Binary,
             8 => Opcode::Close,
             9 => Opcode::Ping,
             10 => Opcode::Pong,
-            _ => Opcode::Reserved,
+            op => Opcode::Reserved(op),
+        }
+    }
+
+    /// Returns the raw value of this opcode.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Opcode;
+    ///
+    /// assert_eq!(0x9, Opcode::Ping.as_u8());
+    /// assert_eq!(0xB, Opcode::Reserved(0xB).as_u8());
+    /// ```
+    pub fn as_u8(self) -> u8 {
+        match self {
+            Opcode::Continue => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+            Opcode::Reserved(op) => op,
+        }
+    }
+
+    /// Returns true for control opcodes, including the reserved ones
+    /// (0x8-0xF).
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Opcode;
+    ///
+    /// assert!(Opcode::Ping.is_control());
+    /// assert!(Opcode::Reserved(0xB).is_control());
+    /// assert!(!Opcode::Text.is_control());
+    /// ```
+    pub fn is_control(self) -> bool {
+        self.as_u8() & 0x8 != 0
+    }
+
+    /// Returns true for data opcodes, including continuations and the
+    /// reserved ones (0x0-0x7).
+    pub fn is_data(self) -> bool {
+        !self.is_control()
+    }
+
+    /// Converts a raw value into an opcode, rejecting reserved opcodes.
+    ///
+    /// This is the strict counterpart of `From<u8>`, which keeps reserved
+    /// opcodes and so leaves `TryFrom<u8>` infallible.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::InvalidOpcode`] for a reserved opcode or a value
+    /// that doesn't fit in 4 bits.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{FrameError, Opcode};
+    ///
+    /// assert_eq!(Ok(Opcode::Close), Opcode::try_from_u8(0x8));
+    /// assert_eq!(Err(FrameError::InvalidOpcode), Opcode::try_from_u8(0x3));
+    /// ```
+    pub fn try_from_u8(opcode: u8) -> Result<Opcode, FrameError> {
+        match Opcode::from_u8(opcode) {
+            _ if opcode > 0xF => Err(FrameError::InvalidOpcode),
+            Opcode::Reserved(_) => Err(FrameError::InvalidOpcode),
+            op => Ok(op),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl From<u8> for Opcode {
+    fn from(opcode: u8) -> Self {
+        Opcode::from_u8(opcode)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Head {
     pub op: Opcode,
     pub finished: bool,
     pub rsv: [bool; 3],
 }
 
+/// A payload length, along with the form it was encoded in.
+///
+/// RFC 6455 asks for the shortest form, but a frame can be re-encoded byte
+/// for byte only if the form it was received in is kept.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Frame, PayloadLen};
+///
+/// let mut f = Frame::empty();
+/// f.decode(&[0x82, 0x7E, 0x00, 0x05]);
+/// assert_eq!(Some(PayloadLen::Extended16(5)), f.wire_len);
+/// assert_eq!(Some(PayloadLen::Short(5)), f.wire_len.map(|len| PayloadLen::minimal(len.value())));
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PayloadLen {
+    /// A length of at most 125, held in the second byte.
+    Short(u8),
+    /// A length held in a 16-bit extended length field.
+    Extended16(u16),
+    /// A length held in a 64-bit extended length field.
+    Extended64(u64),
+}
+
+impl PayloadLen {
+    /// Returns the shortest form that can hold `len`.
+    pub fn minimal(len: u64) -> Self {
+        match len {
+            0..=125 => PayloadLen::Short(len as u8),
+            126..=0xFFFF => PayloadLen::Extended16(len as u16),
+            _ => PayloadLen::Extended64(len),
+        }
+    }
+
+    /// Returns the length itself.
+    pub fn value(self) -> u64 {
+        match self {
+            PayloadLen::Short(len) => len.into(),
+            PayloadLen::Extended16(len) => len.into(),
+            PayloadLen::Extended64(len) => len,
+        }
+    }
+
+    /// Returns the size in bytes of the extended length field: 0, 2 or 8.
+    pub fn extended_len(self) -> usize {
+        match self {
+            PayloadLen::Short(_) => 0,
+            PayloadLen::Extended16(_) => 2,
+            PayloadLen::Extended64(_) => 8,
+        }
+    }
+}
+
 /// A decoded Frame.
 ///
 /// The optional values will be `None` if a decode was not complete, and did
@@ -135,16 +562,18 @@ pub struct Head {
 ///     }
 /// }
 /// ```
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct Frame {
     /// The head section of a frame.
     pub head: Option<Head>,
     /// An optional mask key to apply over the payload.
     pub mask: Option<[u8; 4]>,
-    /// The payload section of a frame.
+    /// The length of the payload section of a frame.
     ///
-    /// An empty payload is represented as `Some(&[])`.
+    /// The payload itself can be borrowed with [`Frame::payload`].
     pub payload_len: Option<u64>,
+    /// The payload length in the form it was encoded in.
+    pub wire_len: Option<PayloadLen>,
 }
 
 impl<'buf> Frame {
@@ -154,13 +583,417 @@ impl<'buf> Frame {
             head: None,
             mask: None,
             payload_len: None,
+            wire_len: None,
+        }
+    }
+
+    /// Clears every field, as if this `Frame` were newly created.
+    ///
+    /// Decoding does this first, so a `Frame` can be reused across decodes
+    /// without fields from an earlier frame leaking into a later one.
+    pub fn reset(&mut self) {
+        *self = Self::empty();
+    }
+
+    /// Borrows the payload from `buf`, the buffer this frame was decoded from.
+    ///
+    /// Returns `None` until the header and the whole payload are in `buf`.
+    /// The payload is returned as received, so still masked if the frame is.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Frame;
+    ///
+    /// let buf = [0x81, 0x02, b'h', b'i', 0x89];
+    /// let mut f = Frame::empty();
+    /// f.decode(&buf);
+    /// assert_eq!(Some(&b"hi"[..]), f.payload(&buf));
+    /// assert_eq!(None, f.payload(&buf[..3]));
+    /// ```
+    pub fn payload<'a>(&self, buf: &'a [u8]) -> Option<&'a [u8]> {
+        let len = self.payload_len_usize()?;
+        let start = head_len(*buf.get(1)?);
+        buf.get(start..start.checked_add(len)?)
+    }
+
+    /// Copies the unmasked payload from `buf`, the buffer this frame was
+    /// decoded from, into the start of `out`.
+    ///
+    /// Unmasking is done while copying, so the payload is only traversed
+    /// once. Returns the payload length, or `None` until the whole payload is
+    /// in `buf` or if it doesn't fit in `out`.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Frame;
+    ///
+    /// let buf = [0x81, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2];
+    /// let mut f = Frame::empty();
+    /// f.decode(&buf);
+    ///
+    /// let mut out = [0; 16];
+    /// let len = f.payload_into(&buf, &mut out).unwrap();
+    /// assert_eq!(b"hi", &out[..len]);
+    /// ```
+    pub fn payload_into(&self, buf: &[u8], out: &mut [u8]) -> Option<usize> {
+        let payload = self.payload(buf)?;
+        let out = out.get_mut(..payload.len())?;
+        match self.mask {
+            Some(mask) => apply_mask_into(mask, payload, out),
+            None => out.copy_from_slice(payload),
         }
+        Some(payload.len())
     }
+
+    /// Decode a Text frame, returning its payload as a `&str` borrowed from
+    /// `buf`.
+    ///
+    /// Returns `Ok(None)` until the header and the whole payload are in
+    /// `buf`. The payload must already be unmasked. A text message split
+    /// across fragments may split a character too, so fragments should be
+    /// checked with a [`Utf8Validator`] instead.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::InvalidOpcode`] for a frame that isn't Text,
+    /// [`FrameError::InvalidUtf8`] if the payload isn't valid UTF-8, and any
+    /// error from [`decode`](Frame::decode).
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Frame, FrameError};
+    ///
+    /// let mut f = Frame::empty();
+    /// assert_eq!(Ok(Some("hi")), f.decode_text(&[0x81, 0x02, b'h', b'i']));
+    /// assert_eq!(Ok(None), f.decode_text(&[0x81, 0x02, b'h']));
+    /// assert_eq!(Err(FrameError::InvalidUtf8), f.decode_text(&[0x81, 0x01, 0xFF]));
+    /// ```
+    pub fn decode_text<'a>(&mut self, buf: &'a [u8]) -> Result<Option<&'a str>, FrameError> {
+        match self.decode(buf) {
+            Status::Complete(_) => {}
+            Status::Partial(_) => return Ok(None),
+            Status::Error(e) => return Err(e),
+        }
+        if self.head.as_ref().map(|head| head.op) != Some(Opcode::Text) {
+            return Err(FrameError::InvalidOpcode);
+        }
+        match self.payload(buf) {
+            Some(payload) => core::str::from_utf8(payload)
+                .map(Some)
+                .map_err(|_| FrameError::InvalidUtf8),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns an iterator over the payload in `buf`, the buffer this frame
+    /// was decoded from, which unmasks it on the fly.
+    ///
+    /// Returns `None` until the whole payload is in `buf`.
+    pub fn unmasked_payload<'a>(&self, buf: &'a [u8]) -> Option<UnmaskedBytes<'a>> {
+        Some(UnmaskedBytes::new(self.payload(buf)?, self.mask))
+    }
+
+    /// Returns the payload length as a `usize`, or `None` if it wasn't
+    /// decoded.
+    ///
+    /// Lengths are kept as `u64` on every target, so this is where a frame too
+    /// large for a 16 or 32-bit address space is caught.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Frame;
+    ///
+    /// let mut f = Frame::empty();
+    /// f.decode(&[0b10000010, 0b00000011]);
+    /// assert_eq!(Some(Ok(3)), f.payload_size());
+    /// ```
+    pub fn payload_size(&self) -> Option<Result<usize, FrameError>> {
+        self.payload_len
+            .map(|len| usize::try_from(len).map_err(|_| FrameError::PayloadTooLarge))
+    }
+
+    /// Returns the payload length as a `usize`, or `None` if it wasn't decoded
+    /// or doesn't fit in one.
+    ///
+    /// Use [`payload_size`](Frame::payload_size) to tell those cases apart.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::Frame;
+    ///
+    /// let mut f = Frame::empty();
+    /// f.decode(&[0x82, 0x7E, 0x01, 0x00]);
+    /// assert_eq!(Some(256), f.payload_len_usize());
+    /// ```
+    pub fn payload_len_usize(&self) -> Option<usize> {
+        self.payload_size()?.ok()
+    }
+
     /// Try to decode a buffer of bytes into this `Frame`.
+    ///
+    /// Every field is overwritten, so after a [`Status::Partial`] only the
+    /// fields decoded from `buf` are set. This holds for each of the decode
+    /// methods.
+    ///
+    /// Control frames are checked with [`ControlChecks`]. Use
+    /// [`decode_with`](Frame::decode_with) and [`AcceptAll`] to skip that.
     pub fn decode(&mut self, buf: &'buf [u8]) -> Status {
+        self.decode_with(buf, &mut ControlChecks)
+    }
+
+    /// Try to decode a buffer of bytes into this `Frame`, rejecting anything
+    /// RFC 6455 forbids without extensions.
+    pub fn decode_strict(&mut self, buf: &'buf [u8]) -> Status {
+        self.decode_with(buf, &mut Strict)
+    }
+
+    /// Decode a buffer of bytes into this `Frame` without validating it, for
+    /// frames that are known to be well formed.
+    ///
+    /// No policy is consulted and no rule is checked, not even the ones
+    /// [`decode_with`](Frame::decode_with) always enforces. Unlike the other
+    /// decode methods, no fields are set on [`Status::Partial`].
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Frame, Status};
+    ///
+    /// // An oversized Ping, which `decode` would reject.
+    /// let buf = [0x89, 0x7E, 0x01, 0x00];
+    /// let mut f = Frame::empty();
+    /// assert_eq!(Status::Complete(4), f.decode_unchecked(&buf));
+    /// assert_eq!(Some(256), f.payload_len);
+    /// ```
+    pub fn decode_unchecked(&mut self, buf: &[u8]) -> Status {
+        self.reset();
+        let second = match buf.get(1) {
+            Some(&second) if buf.len() >= head_len(second) => second,
+            _ => return Status::Partial(header_needed(buf)),
+        };
+
+        let first = buf[0];
+        self.head = Some(Head {
+            op: Opcode::from_u8(first & 0xF),
+            finished: first_bit(first),
+            rsv: [first & 0x40 != 0, first & 0x20 != 0, first & 0x10 != 0],
+        });
+
+        let wire_len = match second & 0x7F {
+            126 => PayloadLen::Extended16(BigEndian::read_u16(&buf[2..4])),
+            127 => PayloadLen::Extended64(BigEndian::read_u64(&buf[2..10])),
+            l => PayloadLen::Short(l),
+        };
+        self.payload_len = Some(wire_len.value());
+        self.wire_len = Some(wire_len);
+
+        let len = head_len(second);
+        if first_bit(second) {
+            let mut mask = [0; 4];
+            mask.copy_from_slice(&buf[len - 4..len]);
+            self.mask = Some(mask);
+        }
+        Status::Complete(len)
+    }
+
+    /// Try to decode a buffer of bytes into this `Frame`, as received by
+    /// `role`.
+    ///
+    /// Frames whose masking doesn't match the role are rejected, and control
+    /// frames are checked as with [`decode`](Frame::decode).
+    pub fn decode_as(&mut self, buf: &'buf [u8], mut role: Role) -> Status {
+        self.decode_with(buf, &mut role)
+    }
+
+    /// Try to decode a buffer of bytes into this `Frame`, consulting `policy`
+    /// as each part of the header is parsed.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Frame, FrameError, Head, Opcode, ValidationPolicy};
+    ///
+    /// struct TextOnly;
+    ///
+    /// impl ValidationPolicy for TextOnly {
+    ///     fn header(&mut self, frame: &Frame) -> Result<(), FrameError> {
+    ///         match frame.head {
+    ///             Some(Head { op: Opcode::Binary, .. }) => {
+    ///                 Err(FrameError::Rejected("binary frames are not accepted"))
+    ///             }
+    ///             _ => Ok(()),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let buf = [0b10000010, 0b00000000];
+    /// let mut f = Frame::empty();
+    /// assert!(f.decode_with(&buf, &mut TextOnly).is_error());
+    /// ```
+    pub fn decode_with<P>(&mut self, buf: &'buf [u8], policy: &mut P) -> Status
+    where
+        P: ValidationPolicy + ?Sized,
+    {
+        self.decode_recorded(buf, policy, true)
+    }
+
+    /// Decodes a header as with [`decode_with`](Frame::decode_with), only
+    /// rejecting a length with its most significant bit set if `reject_msb`.
+    pub(crate) fn decode_recorded<P>(
+        &mut self,
+        buf: &'buf [u8],
+        policy: &mut P,
+        reject_msb: bool,
+    ) -> Status
+    where
+        P: ValidationPolicy + ?Sized,
+    {
+        let status = self.decode_header(buf, policy, reject_msb);
+        #[cfg(feature = "metrics")]
+        metrics::record(self, &status);
+        status
+    }
+
+    /// Try to decode the header at the start of several buffers, as if they
+    /// were one contiguous buffer.
+    ///
+    /// A header split across buffers is decoded without the caller having to
+    /// copy them together; at most 14 bytes are gathered internally. On
+    /// [`Status::Complete`], the payload starts that many bytes into the
+    /// concatenated buffers.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::IoSlice;
+    /// use ws_frame::{Frame, Status};
+    ///
+    /// let bufs = [IoSlice::new(&[0x82, 0x7E, 0x01]), IoSlice::new(&[0x00, 0xFF])];
+    /// let mut f = Frame::empty();
+    /// assert_eq!(Status::Complete(4), f.decode_vectored(&bufs));
+    /// assert_eq!(Some(256), f.payload_len);
+    /// ```
+    pub fn decode_vectored<B>(&mut self, bufs: &[B]) -> Status
+    where
+        B: Deref<Target = [u8]>,
+    {
+        self.decode_vectored_with(bufs, &mut ControlChecks)
+    }
+
+    /// Try to decode a header read from `input`.
+    ///
+    /// Only the bytes of the header itself are read, so on
+    /// [`Status::Complete`] the payload is next in `input`. On
+    /// [`Status::Partial`] every remaining byte has been read, so a source
+    /// that can't be rewound must be decoded again from the start of the
+    /// header.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Frame, Status};
+    ///
+    /// let (a, b) = (&[0x81, 0x7E][..], &[0x00, 0x80, b'h', b'i'][..]);
+    /// let mut input = a.iter().chain(b).copied();
+    ///
+    /// let mut f = Frame::empty();
+    /// assert_eq!(Status::Complete(4), f.decode_from(&mut input));
+    /// assert_eq!(Some(128), f.payload_len);
+    /// assert_eq!(Some(b'h'), input.next());
+    /// ```
+    pub fn decode_from<I>(&mut self, input: &mut I) -> Status
+    where
+        I: InputSource + ?Sized,
+    {
+        self.decode_from_with(input, &mut ControlChecks)
+    }
+
+    /// Try to decode a header read from `input`, consulting `policy` as with
+    /// [`decode_with`](Frame::decode_with).
+    pub fn decode_from_with<I, P>(&mut self, input: &mut I, policy: &mut P) -> Status
+    where
+        I: InputSource + ?Sized,
+        P: ValidationPolicy + ?Sized,
+    {
+        let mut head = [0; 14];
+        let mut len = input.read_into(&mut head[..2]);
+        if len == 2 {
+            let end = head_len(head[1]);
+            len += input.read_into(&mut head[2..end]);
+        }
+        self.decode_with(&head[..len], policy)
+    }
+
+    /// Try to decode the header at the start of a ring buffer's contents,
+    /// which wrap around from the end of `head` to the start of `tail`.
+    ///
+    /// Use [`payload_split`](Frame::payload_split) to borrow the payload
+    /// afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Frame, Status};
+    ///
+    /// let mut ring = [0; 8];
+    /// // A text frame with the payload "hi", written to the last 3 bytes and
+    /// // wrapping around to the start.
+    /// ring[5..].copy_from_slice(&[0x81, 0x02, b'h']);
+    /// ring[0] = b'i';
+    ///
+    /// let (tail, head) = ring.split_at(5);
+    /// let mut f = Frame::empty();
+    /// assert_eq!(Status::Complete(2), f.decode_split(head, &tail[..1]));
+    /// assert_eq!(Some((&b"h"[..], &b"i"[..])), f.payload_split(head, tail));
+    /// ```
+    pub fn decode_split(&mut self, head: &[u8], tail: &[u8]) -> Status {
+        self.decode_vectored(&[head, tail])
+    }
+
+    /// Borrows the payload of a frame decoded by
+    /// [`decode_split`](Frame::decode_split), as the parts in `head` and
+    /// `tail`.
+    ///
+    /// Returns `None` if the header wasn't decoded or the payload isn't
+    /// complete.
+    pub fn payload_split<'a>(
+        &self,
+        head: &'a [u8],
+        tail: &'a [u8],
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        let len = self.payload_len_usize()?;
+        let second = head.get(1).or_else(|| tail.get(1 - head.len()))?;
+        let start = head_len(*second);
+        let end = start.checked_add(len)?;
+
+        let first = head.get(start.min(head.len())..end.min(head.len()))?;
+        let rest = end.saturating_sub(head.len().max(start));
+        let skip = start.saturating_sub(head.len());
+        Some((first, tail.get(skip..skip.checked_add(rest)?)?))
+    }
+
+    /// Try to decode the header at the start of several buffers, consulting
+    /// `policy` as with [`decode_with`](Frame::decode_with).
+    pub fn decode_vectored_with<B, P>(&mut self, bufs: &[B], policy: &mut P) -> Status
+    where
+        B: Deref<Target = [u8]>,
+        P: ValidationPolicy + ?Sized,
+    {
+        let mut head = [0; 14];
+        let mut len = 0;
+        for buf in bufs {
+            let n = buf.len().min(head.len() - len);
+            head[len..len + n].copy_from_slice(&buf[..n]);
+            len += n;
+            if len == head.len() {
+                break;
+            }
+        }
+        self.decode_with(&head[..len], policy)
+    }
+
+    fn decode_header<P>(&mut self, buf: &'buf [u8], policy: &mut P, reject_msb: bool) -> Status
+    where
+        P: ValidationPolicy + ?Sized,
+    {
+        self.reset();
         let mut bytes = Bytes::new(buf);
 
-        let first = unwrap!(bytes.next());
+        let first = unwrap!(bytes.next(), buf);
         let rsv_bits = first >> 4 & 0x7u8;
 
         let mut rsv = [false; 3];
@@ -169,29 +1002,89 @@ impl<'buf> Frame {
         }
 
         self.head = Some(Head {
-            op: Opcode::from(first & 0xF),
+            op: Opcode::from_u8(first & 0xF),
             finished: first_bit(first),
             rsv,
         });
+        check!(policy.rsv(rsv));
 
-        let second = unwrap!(bytes.next());
-        self.payload_len = Some(match second & 0x3F {
-            126 => unwrap!(bytes.slice_to(4).map(BigEndian::read_u64)),
-            // TODO validate most-sig bit == 0
-            127 => unwrap!(bytes.slice_to(8).map(BigEndian::read_u64)),
-            l => l as u64,
-        });
+        let second = unwrap!(bytes.next(), buf);
+        let wire_len = match second & 0x7F {
+            126 => PayloadLen::Extended16(unwrap!(bytes.read_u16_be(), buf)),
+            127 => PayloadLen::Extended64(unwrap!(bytes.read_u64_be(), buf)),
+            l => PayloadLen::Short(l),
+        };
+        let (payload_len, extended) = (wire_len.value(), wire_len.extended_len());
+        self.payload_len = Some(payload_len);
+        self.wire_len = Some(wire_len);
+        // RFC 6455 §5.2: the most significant bit of a 64-bit length must be 0.
+        if reject_msb && payload_len >> 63 != 0 {
+            return Status::Error(FrameError::LengthMsb);
+        }
+        check!(policy.length(payload_len, extended));
+
+        if let Some(head) = self.head.as_ref().filter(|head| head.op.is_control()) {
+            check!(policy.control(head, payload_len));
+        }
 
         if first_bit(second) {
             let mut mask = [0; 4];
-            mask.copy_from_slice(unwrap!(bytes.slice_to(4)));
+            mask.copy_from_slice(unwrap!(bytes.slice_to(4), buf));
             self.mask = Some(mask);
         }
 
+        check!(policy.header(self));
+
+        paranoid_assert!(bytes.pos() <= buf.len());
+        paranoid_assert!(
+            bytes.pos() == 2 + extended + if self.mask.is_some() { 4 } else { 0 },
+            "consumed bytes don't match the header layout"
+        );
+        paranoid_assert!(extended != 0 || payload_len <= 125);
         Status::Complete(bytes.pos())
     }
 }
 
+/// Returns the total length of a frame's header, given its first two bytes.
+///
+/// That's 2, 4 or 10 bytes depending on the length form, plus 4 if the frame
+/// is masked, so a reader can ask for exactly the rest of the header instead
+/// of reading speculatively.
+///
+/// # Example
+/// ```
+/// use ws_frame::header_len_hint;
+///
+/// assert_eq!(2, header_len_hint([0x89, 0x00]));
+/// assert_eq!(8, header_len_hint([0x82, 0xFE]));
+/// assert_eq!(14, header_len_hint([0x82, 0xFF]));
+/// ```
+pub fn header_len_hint(first_two: [u8; 2]) -> usize {
+    head_len(first_two[1])
+}
+
+/// Returns the total header length given the second byte of a frame.
+pub(crate) fn head_len(second: u8) -> usize {
+    let len = match second & 0x7F {
+        126 => 4,
+        127 => 10,
+        _ => 2,
+    };
+    if second & 0x80 != 0 {
+        len + 4
+    } else {
+        len
+    }
+}
+
+/// Returns how many more bytes the header starting `buf` needs.
+fn header_needed(buf: &[u8]) -> usize {
+    match buf.get(1) {
+        Some(&second) => head_len(second).saturating_sub(buf.len()),
+        None => 2 - buf.len(),
+    }
+}
+
 #[inline]
 fn first_bit(byte: u8) -> bool {
     byte >> 7 == 1u8
@@ -217,4 +1110,118 @@ mod tests {
             used
         );
     }
+
+    #[test]
+    fn opcode_conversions() {
+        for raw in 0..=0xF {
+            let op = Opcode::from_u8(raw);
+            assert_eq!(raw, op.as_u8());
+            assert_eq!(raw >= 8, op.is_control());
+            assert_eq!(op.is_data(), !op.is_control());
+            assert_eq!(op, Opcode::from(raw));
+            assert_eq!(
+                matches!(op, Opcode::Reserved(_)),
+                Opcode::try_from_u8(raw).is_err()
+            );
+        }
+        assert_eq!(Opcode::Continue, Opcode::from_u8(0x10));
+        assert_eq!(Err(FrameError::InvalidOpcode), Opcode::try_from_u8(0x10));
+    }
+
+    #[test]
+    fn vectored_headers() {
+        let mut f = Frame::empty();
+        let bufs: [&[u8]; 4] = [&[0x82], &[], &[0xFE, 0x00], &[0x03, 1, 2, 3, 4, 0xFF]];
+        assert_eq!(Status::Complete(8), f.decode_vectored(&bufs));
+        assert_eq!((Some(3), Some([1, 2, 3, 4])), (f.payload_len, f.mask));
+
+        assert_eq!(Status::Partial(5), f.decode_vectored(&bufs[..3]));
+    }
+
+    #[test]
+    fn split_payloads() {
+        let mut f = Frame::empty();
+        let (head, tail) = (&[0x81][..], &[0x02, b'h', b'i', 0x89][..]);
+        assert_eq!(Status::Complete(2), f.decode_split(head, tail));
+        assert_eq!(Some((&[][..], &b"hi"[..])), f.payload_split(head, tail));
+        assert_eq!(None, f.payload_split(head, &tail[..2]));
+
+        let (head, tail) = (&[0x81, 0x02, b'h', b'i'][..], &[][..]);
+        assert_eq!(Status::Complete(2), f.decode_split(head, tail));
+        assert_eq!(Some((&b"hi"[..], &[][..])), f.payload_split(head, tail));
+    }
+
+    #[test]
+    fn decodes_from_sources() {
+        let buf = [0x89, 0x84, 1, 2, 3, 4, 0xFF];
+        let mut bytes = Bytes::new(&buf);
+        let mut f = Frame::empty();
+        assert_eq!(Status::Complete(6), f.decode_from(&mut bytes));
+        assert_eq!((Some([1, 2, 3, 4]), 1), (f.mask, bytes.remaining()));
+
+        let mut input = buf[..4].iter().copied();
+        assert_eq!(Status::Partial(2), f.decode_from(&mut input));
+    }
+
+    #[test]
+    fn reuse_clears_stale_fields() {
+        let mut f = Frame::default();
+        assert!(f.decode(&[0x89, 0x80, 1, 2, 3, 4]).is_complete());
+        assert!(f.decode(&[0x8A, 0x00]).is_complete());
+        assert_eq!(None, f.mask);
+
+        assert!(f.decode(&[0x82]).is_partial());
+        assert_eq!(
+            (Some(Opcode::Binary), None),
+            (f.head.as_ref().map(|h| h.op), f.payload_len)
+        );
+
+        f.reset();
+        assert_eq!(Frame::empty(), f);
+    }
+
+    #[test]
+    fn unchecked_matches_checked() {
+        let frames: [&[u8]; 4] = [
+            &[0xC1, 0x00],
+            &[0x02, 0xFE, 0x01, 0x00, 1, 2, 3, 4],
+            &[0x82, 0x7F, 0, 0, 0, 0, 0, 1, 0, 0],
+            &[0x8A, 0x80, 1, 2, 3],
+        ];
+        for buf in &frames {
+            let (mut checked, mut unchecked) = (Frame::empty(), Frame::empty());
+            let status = checked.decode_with(buf, &mut AcceptAll);
+            assert_eq!(status, unchecked.decode_unchecked(buf));
+            if status.is_complete() {
+                assert_eq!(checked, unchecked);
+            }
+        }
+    }
+
+    #[test]
+    fn extended_lengths() {
+        let mut f = Frame::empty();
+        assert_eq!(Status::Complete(4), f.decode(&[0x82, 0x7E, 0x01, 0x00]));
+        assert_eq!(Some(256), f.payload_len);
+
+        let long = [0x82, 0x7F, 0, 0, 0, 0, 0, 1, 0, 0];
+        assert_eq!(Status::Complete(10), f.decode(&long));
+        assert_eq!(Some(0x10000), f.payload_len);
+
+        // 5 fits in the 7-bit form, so only strict decoding rejects these.
+        let padded = [0x82, 0x7F, 0, 0, 0, 0, 0, 0, 0, 5];
+        assert_eq!(Status::Complete(10), f.decode(&padded));
+        assert_eq!(Some(PayloadLen::Extended64(5)), f.wire_len);
+        assert_eq!(
+            Status::Error(FrameError::NonMinimalLength),
+            f.decode_strict(&padded)
+        );
+        assert_eq!(
+            Status::Error(FrameError::NonMinimalLength),
+            f.decode_strict(&[0x82, 0x7E, 0x00, 0x05])
+        );
+
+        let msb = [0x82, 0x7F, 0x80, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(Status::Error(FrameError::LengthMsb), f.decode(&msb));
+    }
 }
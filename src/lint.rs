@@ -0,0 +1,146 @@
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::{CloseCode, Frame, FrameError, Opcode, Status, ValidationPolicy};
+
+/// A conformance violation reported by [`Frame::lint`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Anomaly {
+    /// One or more RSV bits were set.
+    ReservedBits([bool; 3]),
+    /// The payload length used a longer encoding than necessary.
+    NonMinimalLength {
+        /// The decoded payload length.
+        payload_len: u64,
+        /// The size in bytes of the extended length field that was used.
+        extended: usize,
+    },
+    /// A 64-bit payload length had its most significant bit set.
+    LengthMsb,
+    /// A Close frame carried a one byte payload, which can't hold a code.
+    TruncatedCloseCode,
+    /// A Close frame carried a status code that must not be sent on the wire.
+    InvalidCloseCode(u16),
+}
+
+struct Lint<F> {
+    report: F,
+}
+
+impl<F: FnMut(Anomaly)> ValidationPolicy for Lint<F> {
+    fn rsv(&mut self, rsv: [bool; 3]) -> Result<(), FrameError> {
+        if rsv != [false; 3] {
+            (self.report)(Anomaly::ReservedBits(rsv));
+        }
+        Ok(())
+    }
+
+    fn length(&mut self, payload_len: u64, extended: usize) -> Result<(), FrameError> {
+        let minimal = match payload_len {
+            0..=125 => 0,
+            126..=0xFFFF => 2,
+            _ => 8,
+        };
+        if extended > minimal {
+            (self.report)(Anomaly::NonMinimalLength {
+                payload_len,
+                extended,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Frame {
+    /// Decode a buffer of bytes into this `Frame`, reporting conformance
+    /// violations to `report` instead of failing.
+    ///
+    /// The close code of a Close frame is checked when its first two payload
+    /// bytes are present in `buf`.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Anomaly, Frame};
+    ///
+    /// let buf = [0b11000010, 0b00000000];
+    /// let mut f = Frame::empty();
+    /// let mut anomalies = Vec::new();
+    ///
+    /// assert!(f.lint(&buf, |a| anomalies.push(a)).is_complete());
+    /// assert_eq!(vec![Anomaly::ReservedBits([true, false, false])], anomalies);
+    /// ```
+    pub fn lint<F: FnMut(Anomaly)>(&mut self, buf: &[u8], report: F) -> Status {
+        let mut lint = Lint { report };
+        // Linting keeps going past a length with its MSB set, which every
+        // other decode method rejects.
+        let status = self.decode_recorded(buf, &mut lint, false);
+        if status.is_complete() && self.payload_len.is_some_and(|len| len >> 63 != 0) {
+            (lint.report)(Anomaly::LengthMsb);
+        }
+
+        if let (Status::Complete(len), Some(head)) = (&status, &self.head) {
+            if head.op == Opcode::Close {
+                match self.payload_len {
+                    Some(1) => (lint.report)(Anomaly::TruncatedCloseCode),
+                    Some(_) => {
+                        if let Some(code) = buf.get(*len..*len + 2) {
+                            let mut code = [code[0], code[1]];
+                            if let Some(mask) = self.mask {
+                                code[0] ^= mask[0];
+                                code[1] ^= mask[1];
+                            }
+                            let code = BigEndian::read_u16(&code);
+                            if !CloseCode::from(code).is_allowed_on_wire() {
+                                (lint.report)(Anomaly::InvalidCloseCode(code));
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(buf: &[u8]) -> Option<Anomaly> {
+        let mut anomaly = None;
+        assert!(Frame::empty()
+            .lint(buf, |a| assert_eq!(None, anomaly.replace(a)))
+            .is_complete());
+        anomaly
+    }
+
+    #[test]
+    fn non_minimal_length() {
+        assert_eq!(
+            Some(Anomaly::NonMinimalLength {
+                payload_len: 5,
+                extended: 2
+            }),
+            lint(&[0b10000010, 126, 0, 5])
+        );
+        assert_eq!(None, lint(&[0b10000010, 126, 0, 126]));
+        assert_eq!(
+            Some(Anomaly::LengthMsb),
+            lint(&[0b10000010, 127, 0x80, 0, 0, 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn close_codes() {
+        assert_eq!(
+            Some(Anomaly::InvalidCloseCode(1005)),
+            lint(&[0b10001000, 2, 0x03, 0xED])
+        );
+        assert_eq!(None, lint(&[0b10001000, 2, 0x03, 0xE8]));
+        assert_eq!(
+            Some(Anomaly::TruncatedCloseCode),
+            lint(&[0b10001000, 1, 0x03])
+        );
+    }
+}
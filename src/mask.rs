@@ -0,0 +1,324 @@
+use core::convert::TryInto;
+
+/// Masks or unmasks `payload` in place with `key`.
+///
+/// Masking is its own inverse, so this both masks a client's outgoing
+/// payload and unmasks a received one. The aligned middle of the payload is
+/// processed 8 bytes at a time, and only its unaligned ends byte by byte.
+///
+/// With the `simd` feature, SSE2, AVX2 or NEON instructions are used where the
+/// target supports them, with AVX2 detected at runtime when `std` is enabled.
+///
+/// # Example
+/// ```
+/// use ws_frame::{apply_mask, Frame};
+///
+/// let mut buf = [0x81, 0x82, 1, 2, 3, 4, b'h' ^ 1, b'i' ^ 2];
+/// let mut f = Frame::empty();
+/// let len = f.decode(&buf).unwrap();
+///
+/// apply_mask(f.mask.unwrap(), &mut buf[len..]);
+/// assert_eq!(b"hi", &buf[len..]);
+/// ```
+pub fn apply_mask(key: [u8; 4], payload: &mut [u8]) {
+    #[cfg(feature = "simd")]
+    let payload = {
+        let len = crate::simd::mask_prefix(key, payload);
+        &mut payload[len..]
+    };
+
+    // SAFETY: every bit pattern is a valid u64.
+    let (head, words, tail) = unsafe { payload.align_to_mut::<u64>() };
+    mask_bytes(key, head);
+
+    let mut key = key;
+    key.rotate_left(head.len() % 4);
+    let word = u64::from_ne_bytes([
+        key[0], key[1], key[2], key[3], key[0], key[1], key[2], key[3],
+    ]);
+    for w in words {
+        *w ^= word;
+    }
+    mask_bytes(key, tail);
+}
+
+/// Copies `src` into the start of `dst`, masking or unmasking it with `key`
+/// in the same pass.
+///
+/// # Panics
+/// Panics if `dst` is shorter than `src`.
+///
+/// # Example
+/// ```
+/// use ws_frame::apply_mask_into;
+///
+/// let mut out = [0; 2];
+/// apply_mask_into([1, 2, 3, 4], &[b'h' ^ 1, b'i' ^ 2], &mut out);
+/// assert_eq!(*b"hi", out);
+/// ```
+pub fn apply_mask_into(key: [u8; 4], src: &[u8], dst: &mut [u8]) {
+    let dst = &mut dst[..src.len()];
+    let word = u64::from_ne_bytes([
+        key[0], key[1], key[2], key[3], key[0], key[1], key[2], key[3],
+    ]);
+    let mut src_words = src.chunks_exact(8);
+    let mut dst_words = dst.chunks_exact_mut(8);
+    for (s, d) in (&mut src_words).zip(&mut dst_words) {
+        let masked = u64::from_ne_bytes(s.try_into().unwrap()) ^ word;
+        d.copy_from_slice(&masked.to_ne_bytes());
+    }
+    let tail = src_words.remainder();
+    for (i, (d, s)) in dst_words.into_remainder().iter_mut().zip(tail).enumerate() {
+        *d = s ^ key[i % 4];
+    }
+}
+
+/// An iterator over a masked payload, yielding it unmasked.
+///
+/// Bytes are unmasked as they're read, so the payload can be consumed without
+/// copying or mutating it. With `std`, it's also an [`io::Read`](std::io::Read).
+///
+/// # Example
+/// ```
+/// use ws_frame::UnmaskedBytes;
+///
+/// let payload = [b'h' ^ 1, b'i' ^ 2];
+/// let bytes = UnmaskedBytes::new(&payload, Some([1, 2, 3, 4]));
+/// assert_eq!(b"hi".to_vec(), bytes.collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnmaskedBytes<'a> {
+    payload: &'a [u8],
+    key: [u8; 4],
+    pos: usize,
+}
+
+impl<'a> UnmaskedBytes<'a> {
+    /// Creates an iterator over `payload`, unmasking it with `mask` if one is
+    /// given.
+    pub fn new(payload: &'a [u8], mask: Option<[u8; 4]>) -> Self {
+        Self {
+            payload,
+            key: mask.unwrap_or([0; 4]),
+            pos: 0,
+        }
+    }
+}
+
+impl Iterator for UnmaskedBytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.payload.get(self.pos)? ^ self.key[self.pos % 4];
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.payload.len() - self.pos;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for UnmaskedBytes<'_> {}
+
+#[cfg(feature = "std")]
+impl std::io::Read for UnmaskedBytes<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let rest = &self.payload[self.pos..];
+        let n = rest.len().min(buf.len());
+        let mut key = self.key;
+        key.rotate_left(self.pos % 4);
+        apply_mask_into(key, &rest[..n], buf);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+fn mask_bytes(key: [u8; 4], bytes: &mut [u8]) {
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b ^= key[i % 4];
+    }
+}
+
+/// The size of the chunks masked on separate threads by
+/// [`apply_mask_parallel`]. It's a multiple of 4, so every chunk starts at the
+/// same key offset.
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK: usize = 512 * 1024;
+
+/// Masks or unmasks `payload` in place with `key`, splitting it across
+/// rayon's thread pool.
+///
+/// This pays off for payloads of several megabytes. Shorter ones are masked
+/// on the calling thread, exactly as with [`apply_mask`].
+///
+/// # Example
+/// ```
+/// use ws_frame::{apply_mask, apply_mask_parallel};
+///
+/// let mut a = vec![7; 3 << 20];
+/// let mut b = a.clone();
+/// apply_mask([1, 2, 3, 4], &mut a);
+/// apply_mask_parallel([1, 2, 3, 4], &mut b);
+/// assert_eq!(a, b);
+/// ```
+#[cfg(feature = "rayon")]
+pub fn apply_mask_parallel(key: [u8; 4], payload: &mut [u8]) {
+    use rayon::prelude::*;
+
+    if payload.len() <= PARALLEL_CHUNK {
+        return apply_mask(key, payload);
+    }
+    payload
+        .par_chunks_mut(PARALLEL_CHUNK)
+        .enumerate()
+        .for_each(|(i, chunk)| apply_mask_offset(key, (i * PARALLEL_CHUNK) as u64, chunk));
+}
+
+/// Masks or unmasks `chunk` in place with `key`, where `chunk` starts
+/// `offset` bytes into the payload.
+///
+/// This continues the mask where the previous chunk left off, so a payload
+/// arriving in pieces can be unmasked as each one arrives.
+///
+/// # Example
+/// ```
+/// use ws_frame::{apply_mask, apply_mask_offset};
+///
+/// let key = [1, 2, 3, 4];
+/// let mut whole = *b"hello";
+/// apply_mask(key, &mut whole);
+///
+/// let (mut a, mut b) = (*b"he", *b"llo");
+/// apply_mask_offset(key, 0, &mut a);
+/// apply_mask_offset(key, 2, &mut b);
+/// assert_eq!(whole, [a[0], a[1], b[0], b[1], b[2]]);
+/// ```
+pub fn apply_mask_offset(key: [u8; 4], offset: u64, chunk: &mut [u8]) {
+    let mut key = key;
+    key.rotate_left((offset % 4) as usize);
+    apply_mask(key, chunk);
+}
+
+/// A source of mask keys for client frames.
+///
+/// Clients must mask every frame with a fresh, unpredictable key. Implement
+/// this to use a custom entropy source, such as a hardware TRNG. Closures
+/// returning a key implement it too, which is handy for deterministic tests.
+///
+/// # Example
+/// ```
+/// use ws_frame::MaskKeyGenerator;
+///
+/// let mut n = 0u32;
+/// let mut counter = || {
+///     n += 1;
+///     n.to_be_bytes()
+/// };
+/// assert_eq!([0, 0, 0, 1], counter.next_key());
+/// assert_eq!([0, 0, 0, 2], counter.next_key());
+/// ```
+pub trait MaskKeyGenerator {
+    /// Returns the next mask key.
+    fn next_key(&mut self) -> [u8; 4];
+}
+
+impl<F: FnMut() -> [u8; 4]> MaskKeyGenerator for F {
+    fn next_key(&mut self) -> [u8; 4] {
+        self()
+    }
+}
+
+/// Mask keys drawn from the operating system's secure random number
+/// generator.
+///
+/// # Panics
+/// [`next_key`](MaskKeyGenerator::next_key) panics if the operating system
+/// fails to provide random data.
+#[cfg(feature = "getrandom")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct OsMaskKeys;
+
+#[cfg(feature = "getrandom")]
+impl MaskKeyGenerator for OsMaskKeys {
+    fn next_key(&mut self) -> [u8; 4] {
+        let mut key = [0; 4];
+        getrandom::getrandom(&mut key).expect("failed to generate a mask key");
+        key
+    }
+}
+
+/// Mask keys drawn from a [`rand_core`] random number generator, for targets
+/// without an operating system RNG.
+///
+/// The generator should be a cryptographically secure one, as predictable
+/// keys defeat the purpose of masking.
+#[cfg(feature = "rand_core")]
+#[derive(Debug, Default, Clone)]
+pub struct RngMaskKeys<R>(pub R);
+
+#[cfg(feature = "rand_core")]
+impl<R: rand_core::RngCore> MaskKeyGenerator for RngMaskKeys<R> {
+    fn next_key(&mut self) -> [u8; 4] {
+        self.0.next_u32().to_ne_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reads_and_iterates_alike() {
+        use std::io::Read;
+
+        let payload: Vec<u8> = (0..11).collect();
+        let key = Some([9, 8, 7, 6]);
+        let mut bytes = UnmaskedBytes::new(&payload, key);
+        assert_eq!(Some(9), bytes.next());
+
+        let mut out = [0; 3];
+        assert_eq!(3, bytes.read(&mut out).unwrap());
+        let mut rest = Vec::new();
+        bytes.read_to_end(&mut rest).unwrap();
+
+        let expected: Vec<u8> = UnmaskedBytes::new(&payload, key).collect();
+        assert_eq!(&expected[1..4], out);
+        assert_eq!(&expected[4..], &rest[..]);
+    }
+
+    #[test]
+    fn masks_while_copying() {
+        let key = [0x12, 0x34, 0x56, 0x78];
+        let src = [0xAB; 21];
+        for len in 0..src.len() {
+            let mut expected = [0; 21];
+            expected[..len].copy_from_slice(&src[..len]);
+            apply_mask(key, &mut expected[..len]);
+
+            let mut out = [0; 21];
+            apply_mask_into(key, &src[..len], &mut out);
+            assert_eq!(expected, out);
+        }
+    }
+
+    #[test]
+    fn masks_every_length_and_alignment() {
+        let key = [0x12, 0x34, 0x56, 0x78];
+        let mut buf = [0; 40];
+        for start in 0..8 {
+            for len in 0..32 {
+                let payload = &mut buf[start..start + len];
+                for (i, b) in payload.iter_mut().enumerate() {
+                    *b = i as u8;
+                }
+                apply_mask(key, payload);
+                for (i, &b) in payload.iter().enumerate() {
+                    assert_eq!(i as u8 ^ key[i % 4], b);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::encode::encode_control_into;
+use crate::{
+    apply_mask, encode_close, encode_head, parse_close, CloseCode, Frame, FrameError, Head, Opcode,
+    Status,
+};
+
+/// A complete message borrowed from a buffer.
+///
+/// This is a single finished frame seen at message granularity, with a
+/// Text payload checked to be UTF-8 and a Close payload parsed into its code
+/// and reason. Fragmented messages are put back together by a
+/// [`MessageAssembler`](crate::MessageAssembler) instead.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Frame, Message};
+///
+/// let mut out = [0; 8];
+/// let len = Message::Text("hi").encode(None, &mut out).unwrap();
+/// assert_eq!(&[0x81, 0x02, b'h', b'i'], &out[..len]);
+///
+/// let mut f = Frame::empty();
+/// assert_eq!(Ok(Some(Message::Text("hi"))), f.decode_message(&out[..len]));
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Message<'a> {
+    /// A UTF-8 text message.
+    Text(&'a str),
+    /// A binary message.
+    Binary(&'a [u8]),
+    /// A Ping, with its application data.
+    Ping(&'a [u8]),
+    /// A Pong, with its application data.
+    Pong(&'a [u8]),
+    /// A Close, with its code and reason if it had a status.
+    Close(Option<(CloseCode, &'a str)>),
+}
+
+impl<'a> Message<'a> {
+    /// Converts a finished frame and its unmasked payload into a message.
+    ///
+    /// # Errors
+    /// Returns [`FrameError::InvalidOpcode`] for a continuation, a fragment
+    /// that isn't finished or a reserved opcode,
+    /// [`FrameError::InvalidUtf8`] for a Text payload that isn't UTF-8, and
+    /// the errors of [`parse_close`] for a Close payload.
+    pub fn from_frame(head: &Head, payload: &'a [u8]) -> Result<Self, FrameError> {
+        if !head.finished {
+            return Err(FrameError::InvalidOpcode);
+        }
+        match head.op {
+            Opcode::Text => core::str::from_utf8(payload)
+                .map(Message::Text)
+                .map_err(|_| FrameError::InvalidUtf8),
+            Opcode::Binary => Ok(Message::Binary(payload)),
+            Opcode::Ping => Ok(Message::Ping(payload)),
+            Opcode::Pong => Ok(Message::Pong(payload)),
+            Opcode::Close => match parse_close(payload)? {
+                (CloseCode::NoStatus, _) => Ok(Message::Close(None)),
+                close => Ok(Message::Close(Some(close))),
+            },
+            _ => Err(FrameError::InvalidOpcode),
+        }
+    }
+
+    /// Returns the opcode of the frame this message is sent in.
+    pub fn opcode(&self) -> Opcode {
+        match self {
+            Message::Text(_) => Opcode::Text,
+            Message::Binary(_) => Opcode::Binary,
+            Message::Ping(_) => Opcode::Ping,
+            Message::Pong(_) => Opcode::Pong,
+            Message::Close(_) => Opcode::Close,
+        }
+    }
+
+    /// Encodes this message as a single finished frame into `out`.
+    ///
+    /// The payload is masked with `mask` if one is given, which clients must
+    /// do. Returns the amount of bytes written.
+    ///
+    /// # Errors
+    /// Returns the errors of [`encode_close`] for a Close,
+    /// [`FrameError::ControlFrameTooLong`] for a Ping or Pong longer than
+    /// 125 bytes, and [`FrameError::BufferTooSmall`] if the frame doesn't fit
+    /// in `out`.
+    pub fn encode(&self, mask: Option<[u8; 4]>, out: &mut [u8]) -> Result<usize, FrameError> {
+        let payload = match *self {
+            Message::Text(text) => text.as_bytes(),
+            Message::Binary(payload) => payload,
+            Message::Ping(payload) | Message::Pong(payload) => {
+                return encode_control_into(self.opcode(), payload, mask, out)
+            }
+            Message::Close(Some((code, reason))) => {
+                return encode_close(code.into(), reason, mask, out)
+            }
+            Message::Close(None) => return encode_control_into(Opcode::Close, &[], mask, out),
+        };
+
+        let head = Head {
+            op: self.opcode(),
+            finished: true,
+            rsv: [false; 3],
+        };
+        let len = encode_head(&head, payload.len() as u64, mask, out)?;
+        let out = out
+            .get_mut(len..len + payload.len())
+            .ok_or(FrameError::BufferTooSmall)?;
+        out.copy_from_slice(payload);
+        if let Some(key) = mask {
+            apply_mask(key, out);
+        }
+        Ok(len + payload.len())
+    }
+
+    /// Copies this message's payload into an [`OwnedMessage`].
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> OwnedMessage {
+        match self {
+            Message::Text(text) => OwnedMessage::Text(text.into()),
+            Message::Binary(payload) => OwnedMessage::Binary(payload.into()),
+            Message::Ping(payload) => OwnedMessage::Ping(payload.into()),
+            Message::Pong(payload) => OwnedMessage::Pong(payload.into()),
+            Message::Close(close) => {
+                OwnedMessage::Close(close.map(|(code, reason)| (code, reason.into())))
+            }
+        }
+    }
+}
+
+/// A complete message owning its payload.
+///
+/// This is the owned form of [`Message`], for keeping a message after the
+/// buffer it was decoded from is reused, and what a
+/// [`MessageAssembler`](crate::MessageAssembler) yields.
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum OwnedMessage {
+    /// A UTF-8 text message.
+    Text(String),
+    /// A binary message.
+    Binary(Vec<u8>),
+    /// A Ping, with its application data.
+    Ping(Vec<u8>),
+    /// A Pong, with its application data.
+    Pong(Vec<u8>),
+    /// A Close, with its code and reason if it had a status.
+    Close(Option<(CloseCode, String)>),
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedMessage {
+    /// Borrows this message as a [`Message`], such as to encode it.
+    pub fn as_message(&self) -> Message<'_> {
+        match self {
+            OwnedMessage::Text(text) => Message::Text(text),
+            OwnedMessage::Binary(payload) => Message::Binary(payload),
+            OwnedMessage::Ping(payload) => Message::Ping(payload),
+            OwnedMessage::Pong(payload) => Message::Pong(payload),
+            OwnedMessage::Close(close) => {
+                Message::Close(close.as_ref().map(|(code, reason)| (*code, &reason[..])))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Message<'_>> for OwnedMessage {
+    fn from(message: Message<'_>) -> Self {
+        message.into_owned()
+    }
+}
+
+impl Frame {
+    /// Decode a single finished frame, returning it as a [`Message`]
+    /// borrowed from `buf`.
+    ///
+    /// Returns `Ok(None)` until the header and the whole payload are in
+    /// `buf`. The payload must already be unmasked.
+    ///
+    /// # Errors
+    /// Returns the errors of [`decode`](Frame::decode) and
+    /// [`Message::from_frame`].
+    pub fn decode_message<'a>(&mut self, buf: &'a [u8]) -> Result<Option<Message<'a>>, FrameError> {
+        match self.decode(buf) {
+            Status::Complete(_) => {}
+            Status::Partial(_) => return Ok(None),
+            Status::Error(e) => return Err(e),
+        }
+        match (&self.head, self.payload(buf)) {
+            (Some(head), Some(payload)) => Message::from_frame(head, payload).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            Message::Text("héllo"),
+            Message::Binary(&[0, 1, 2]),
+            Message::Ping(b"ping"),
+            Message::Pong(&[]),
+            Message::Close(None),
+            Message::Close(Some((CloseCode::GoingAway, "bye"))),
+        ];
+        for message in messages {
+            let mut out = [0; 16];
+            let len = message.encode(None, &mut out).unwrap();
+            let mut frame = Frame::empty();
+            assert_eq!(Ok(Some(message)), frame.decode_message(&out[..len]));
+            #[cfg(feature = "alloc")]
+            assert_eq!(message, message.into_owned().as_message());
+        }
+    }
+
+    #[test]
+    fn rejects_fragments() {
+        let mut frame = Frame::empty();
+        assert_eq!(
+            Err(FrameError::InvalidOpcode),
+            frame.decode_message(&[0x01, 0x01, b'a'])
+        );
+        assert_eq!(
+            Err(FrameError::InvalidOpcode),
+            frame.decode_message(&[0x80, 0x01, b'a'])
+        );
+        assert_eq!(Ok(None), frame.decode_message(&[0x81, 0x02, b'a']));
+    }
+}
@@ -0,0 +1,62 @@
+//! Decoder instrumentation through the `metrics` facade.
+//!
+//! Every decode records:
+//! - `ws_frame_frames_decoded_total`, a counter labelled by `opcode`
+//! - `ws_frame_payload_bytes`, a histogram of declared payload lengths
+//! - `ws_frame_protocol_errors_total`, a counter labelled by error `kind`
+
+use metrics::{counter, histogram};
+
+use crate::{Frame, FrameError, Opcode, Status};
+
+/// Records the outcome of decoding `frame`.
+pub(crate) fn record(frame: &Frame, status: &Status) {
+    match status {
+        Status::Complete(_) => {
+            if let Some(head) = &frame.head {
+                counter!("ws_frame_frames_decoded_total", "opcode" => opcode_name(head.op))
+                    .increment(1);
+            }
+            if let Some(len) = frame.payload_len {
+                histogram!("ws_frame_payload_bytes").record(len as f64);
+            }
+        }
+        Status::Partial(_) => {}
+        Status::Error(e) => {
+            counter!("ws_frame_protocol_errors_total", "kind" => error_kind(e)).increment(1);
+        }
+    }
+}
+
+fn opcode_name(op: Opcode) -> &'static str {
+    match op {
+        Opcode::Continue => "continue",
+        Opcode::Text => "text",
+        Opcode::Binary => "binary",
+        Opcode::Close => "close",
+        Opcode::Ping => "ping",
+        Opcode::Pong => "pong",
+        Opcode::Reserved(_) => "reserved",
+    }
+}
+
+fn error_kind(e: &FrameError) -> &'static str {
+    match e {
+        FrameError::Rejected(_) => "rejected",
+        FrameError::InvalidOpcode => "invalid_opcode",
+        FrameError::ControlFrameTooLong => "control_frame_too_long",
+        FrameError::BufferTooSmall => "buffer_too_small",
+        FrameError::PayloadTooLarge => "payload_too_large",
+        FrameError::ReservedBits => "reserved_bits",
+        FrameError::FragmentedControl => "fragmented_control",
+        FrameError::NonMinimalLength => "non_minimal_length",
+        FrameError::LengthMsb => "length_msb",
+        FrameError::MissingMask => "missing_mask",
+        FrameError::UnexpectedMask => "unexpected_mask",
+        FrameError::PayloadOverLimit => "payload_over_limit",
+        FrameError::LengthFormRejected => "length_form_rejected",
+        FrameError::InvalidCloseCode => "invalid_close_code",
+        FrameError::InvalidUtf8 => "invalid_utf8",
+        FrameError::InvalidLengthForm => "invalid_length_form",
+    }
+}
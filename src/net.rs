@@ -0,0 +1,491 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use crate::blocking::read_head_with;
+use crate::handshake::{
+    find_head_end, parse_connect_response, parse_request, write_connect, write_rejection,
+    write_response, ClientHandshake, HandshakeError, Request, MAX_HEAD_LEN,
+};
+use crate::session::{unmask, Session};
+use crate::{Extensions, Opcode, Quirks, Strict};
+
+/// Connects to a `ws://` URL and performs the client handshake.
+///
+/// # Example
+/// ```no_run
+/// use ws_frame::{connect, Opcode};
+///
+/// let mut conn = connect("ws://localhost:9001/echo").unwrap();
+/// conn.send(Opcode::Text, b"hello").unwrap();
+/// let (op, payload) = conn.receive().unwrap();
+/// ```
+pub fn connect(url: &str) -> io::Result<Connection<TcpStream>> {
+    let url = Url::parse(url)?;
+    if url.secure {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "wss:// URLs need a TLS connection",
+        ));
+    }
+    let stream = TcpStream::connect((url.host, url.port))?;
+    client(stream, url.authority, &url.path)
+}
+
+/// Connects to a `ws://` URL through an HTTP proxy at `proxy`, tunneling with
+/// a `CONNECT` request before the handshake.
+///
+/// `credentials` is a user name and password for the proxy's Basic
+/// authentication.
+///
+/// # Example
+/// ```no_run
+/// use ws_frame::connect_via;
+///
+/// let conn = connect_via("ws://example.com/chat", "proxy.corp:3128", Some(("user", "pass")));
+/// ```
+pub fn connect_via<A: ToSocketAddrs>(
+    url: &str,
+    proxy: A,
+    credentials: Option<(&str, &str)>,
+) -> io::Result<Connection<TcpStream>> {
+    let url = Url::parse(url)?;
+    if url.secure {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "wss:// URLs need a TLS connection",
+        ));
+    }
+    let stream = tunnel(TcpStream::connect(proxy)?, url.host, url.port, credentials)?;
+    client(stream, url.authority, &url.path)
+}
+
+/// Opens a `CONNECT` tunnel to `host` and `port` through a proxy `stream`.
+fn tunnel<S: Read + Write>(
+    stream: S,
+    host: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> io::Result<S> {
+    // IPv6 literals need their brackets back in the request target.
+    let authority = if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+    let mut request = Vec::new();
+    write_connect(&authority, credentials, &mut request);
+
+    let mut stream = BufReader::new(stream);
+    stream.get_mut().write_all(&request)?;
+    stream.get_mut().flush()?;
+
+    let head = read_http_head(&mut stream)?;
+    if let Err(e) = parse_connect_response(&head) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+    if !stream.buffer().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "proxy sent data before the tunnel opened",
+        ));
+    }
+    Ok(stream.into_inner())
+}
+
+/// Performs the client handshake over an established stream.
+///
+/// `host` is sent as the `Host` header, and `path` is the resource requested.
+pub fn client<S: Read + Write>(stream: S, host: &str, path: &str) -> io::Result<Connection<S>> {
+    let handshake = ClientHandshake::new();
+    let mut request = Vec::new();
+    handshake.write_request(host, path, &mut request);
+
+    let mut stream = BufReader::new(stream);
+    stream.get_mut().write_all(&request)?;
+    stream.get_mut().flush()?;
+
+    let head = read_http_head(&mut stream)?;
+    match handshake.parse_response(&head) {
+        Ok(Some(_)) => Ok(Connection::new(stream, true)),
+        Ok(None) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "incomplete handshake response",
+        )),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// Performs the server handshake over an accepted stream.
+///
+/// # Example
+/// ```no_run
+/// use std::net::TcpListener;
+/// use std::thread;
+/// use ws_frame::{accept, Opcode};
+///
+/// let listener = TcpListener::bind("127.0.0.1:9001").unwrap();
+/// for stream in listener.incoming() {
+///     thread::spawn(move || {
+///         let mut conn = accept(stream.unwrap()).unwrap();
+///         while let Ok((op, payload)) = conn.receive() {
+///             if op == Opcode::Close {
+///                 break;
+///             }
+///             conn.send(op, &payload).unwrap();
+///         }
+///     });
+/// }
+/// ```
+pub fn accept<S: Read + Write>(stream: S) -> io::Result<Connection<S>> {
+    accept_with(stream, |_| Ok(None))
+}
+
+/// Performs the server handshake over an accepted stream, letting `callback`
+/// inspect the request first.
+///
+/// The callback can check the request's Origin and pick one of its offered
+/// subprotocols. Returning `Ok(Some(protocol))` accepts the connection with
+/// that subprotocol, `Ok(None)` accepts it without one, and an error refuses
+/// it.
+pub fn accept_with<S, F>(stream: S, callback: F) -> io::Result<Connection<S>>
+where
+    S: Read + Write,
+    F: FnOnce(&Request) -> Result<Option<String>, HandshakeError>,
+{
+    let mut stream = BufReader::new(stream);
+    let head = read_http_head(&mut stream)?;
+
+    let accepted = parse_request(&head).and_then(|request| {
+        // A head that ends early, such as a lone blank line, is malformed.
+        let (request, _) = request.ok_or(HandshakeError::Http(httparse::Error::Token))?;
+        let protocol = callback(&request)?;
+        Ok((request, protocol))
+    });
+    let (request, protocol) = match accepted {
+        Ok(accepted) => accepted,
+        Err(e) => {
+            let mut response = Vec::new();
+            write_rejection(&e, &mut response);
+            stream.get_mut().write_all(&response)?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+    };
+
+    let mut response = Vec::new();
+    write_response(&request.key, protocol.as_deref(), &mut response);
+    stream.get_mut().write_all(&response)?;
+    stream.get_mut().flush()?;
+    Ok(Connection::new(stream, false))
+}
+
+/// Reads an HTTP message head, leaving anything after it buffered in
+/// `stream`.
+pub(crate) fn read_http_head<S: Read>(stream: &mut BufReader<S>) -> io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    loop {
+        let buf = stream.fill_buf()?;
+        if buf.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let start = head.len();
+        let read = buf.len();
+        head.extend_from_slice(buf);
+        if let Some(end) = find_head_end(&head, start) {
+            stream.consume(read - (head.len() - end));
+            head.truncate(end);
+            return Ok(head);
+        }
+        stream.consume(read);
+
+        if head.len() > MAX_HEAD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "handshake head is too long",
+            ));
+        }
+    }
+}
+
+/// A blocking WebSocket connection.
+///
+/// Clients mask every frame they send, as RFC 6455 requires, and servers
+/// send them unmasked.
+#[derive(Debug)]
+pub struct Connection<S> {
+    stream: BufReader<S>,
+    session: Session,
+    extensions: Extensions,
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub(crate) fn new(stream: BufReader<S>, client: bool) -> Self {
+        Self {
+            stream,
+            session: Session::new(client),
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// Sets the largest message [`receive`](Connection::receive) accepts.
+    pub fn set_max_message(&mut self, max: usize) {
+        self.session.max_message = max;
+    }
+
+    /// Sets the leniencies used when validating the peer's frames.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.session.quirks = quirks;
+    }
+
+    /// Sends `payload` in a single frame.
+    pub fn send(&mut self, op: Opcode, payload: &[u8]) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.session.encode(op, payload, &mut buf)?;
+        self.write(&buf)
+    }
+
+    /// Starts the closing handshake by sending a Close frame.
+    ///
+    /// The reason is truncated to fit in the frame. Keep calling
+    /// [`receive`](Connection::receive) until the peer's Close arrives.
+    pub fn close(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.session.close(code, reason, &mut buf)?;
+        self.write(&buf)
+    }
+
+    /// Receives the next message.
+    ///
+    /// Fragmented messages are reassembled, Pings are answered and Pongs are
+    /// skipped, so this only returns Text, Binary and Close messages. A Close
+    /// from the peer is echoed before it is returned, unless one was already
+    /// sent.
+    pub fn receive(&mut self) -> io::Result<(Opcode, Vec<u8>)> {
+        let mut reply = Vec::new();
+        loop {
+            let frame = match read_head_with(&mut self.stream, &mut Strict)? {
+                Ok(frame) => frame,
+                Err(e) => {
+                    let e = self.session.fail(e, &mut reply);
+                    // The connection is failing either way, so a lost Close
+                    // doesn't change the outcome.
+                    let _ = self.write(&reply);
+                    return Err(e);
+                }
+            };
+            let head = frame.head.as_ref().expect("decoded a complete header");
+            let mut payload = vec![0; self.session.payload_len(&frame)?];
+            self.stream.read_exact(&mut payload)?;
+            unmask(frame.mask, &mut payload);
+
+            let message = self.session.receive(head, payload, &mut reply)?;
+            if !reply.is_empty() {
+                self.write(&reply)?;
+                reply.clear();
+            }
+            if let Some(message) = message {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Returns the state extensions and middleware keep on this connection.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns a mutable reference to this connection's extension state.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.stream.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.stream.get_mut()
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(buf)?;
+        stream.flush()
+    }
+}
+
+/// The parts of a `ws://` or `wss://` URL needed to connect.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Url<'a> {
+    pub(crate) secure: bool,
+    pub(crate) host: &'a str,
+    pub(crate) port: u16,
+    pub(crate) authority: &'a str,
+    pub(crate) path: String,
+}
+
+impl<'a> Url<'a> {
+    pub(crate) fn parse(url: &'a str) -> io::Result<Self> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidInput, msg);
+
+        // The host and path are copied into the request head, where a line
+        // break or space would let the URL inject headers.
+        if url.bytes().any(|b| b.is_ascii_control() || b == b' ') {
+            return Err(invalid("invalid character in URL"));
+        }
+
+        let (secure, rest) = match url.find("://") {
+            Some(i) if url[..i].eq_ignore_ascii_case("ws") => (false, &url[i + 3..]),
+            Some(i) if url[..i].eq_ignore_ascii_case("wss") => (true, &url[i + 3..]),
+            Some(_) => return Err(invalid("unsupported URL scheme")),
+            None => return Err(invalid("missing URL scheme")),
+        };
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('/') => (&rest[..i], rest[i..].to_owned()),
+            Some(i) => (&rest[..i], format!("/{}", &rest[i..])),
+            None => (rest, "/".to_owned()),
+        };
+
+        // IPv6 literals are bracketed, and may contain colons.
+        let port_sep = match authority.rfind(']') {
+            Some(end) => authority[end..].find(':').map(|i| end + i),
+            None => authority.rfind(':'),
+        };
+        let (host, port) = match port_sep {
+            Some(i) => (
+                &authority[..i],
+                authority[i + 1..]
+                    .parse()
+                    .map_err(|_| invalid("invalid port"))?,
+            ),
+            None => (authority, if secure { 443 } else { 80 }),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+
+        Ok(Url {
+            secure,
+            host,
+            port,
+            authority,
+            path,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stream that reads from `input` and collects what is written.
+    #[derive(Debug)]
+    struct Mock {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Mock {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: io::Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn rejects_empty_heads() {
+        let mut stream = Mock::new(b"\r\n\r\n");
+        let e = accept(&mut stream).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+        assert!(stream.output.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+
+        let e = client(Mock::new(b"\r\n\r\n"), "localhost", "/").unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+
+    #[test]
+    fn fails_on_reserved_bits() {
+        let stream = Mock::new(&[0xC1, 0x80, 0, 0, 0, 0]);
+        let mut conn = Connection::new(BufReader::new(stream), false);
+        let e = conn.receive().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+        assert_eq!(&[0x88, 0x02, 0x03, 0xEA], &conn.get_ref().output[..]);
+    }
+
+    #[test]
+    fn parses_urls() {
+        assert_eq!(
+            Url {
+                secure: false,
+                host: "::1",
+                port: 9001,
+                authority: "[::1]:9001",
+                path: "/chat?room=1".to_owned()
+            },
+            Url::parse("ws://[::1]:9001/chat?room=1").unwrap()
+        );
+        let url = Url::parse("WS://example.com").unwrap();
+        assert_eq!(
+            ("example.com", 80, "/"),
+            (url.host, url.port, &url.path[..])
+        );
+        assert_eq!("/?a=b", Url::parse("ws://example.com?a=b").unwrap().path);
+        let url = Url::parse("wss://example.com/").unwrap();
+        assert_eq!((true, 443), (url.secure, url.port));
+        assert!(Url::parse("http://example.com").is_err());
+        assert!(Url::parse("ws://example.com/\r\nCookie: a=b").is_err());
+        assert!(Url::parse("ws://example.com\n/").is_err());
+    }
+
+    #[test]
+    fn echo_over_loopback() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/echo", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = accept_with(stream, |request| {
+                assert_eq!("/echo", request.path);
+                Ok(None)
+            })
+            .unwrap();
+            loop {
+                let (op, payload) = conn.receive().unwrap();
+                if op == Opcode::Close {
+                    break;
+                }
+                conn.send(op, &payload).unwrap();
+            }
+        });
+
+        let mut conn = connect(&url).unwrap();
+        conn.send(Opcode::Text, b"hello").unwrap();
+        assert_eq!((Opcode::Text, b"hello".to_vec()), conn.receive().unwrap());
+
+        conn.close(1000, "bye").unwrap();
+        let (op, payload) = conn.receive().unwrap();
+        assert_eq!((Opcode::Close, &[0x03, 0xE8][..]), (op, &payload[..]));
+        server.join().unwrap();
+    }
+}
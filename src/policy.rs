@@ -0,0 +1,163 @@
+use crate::{Frame, FrameError, Head, Opcode};
+
+/// Custom checks run by [`Frame::decode_with`] as a frame is decoded.
+///
+/// Every method defaults to accepting, so a policy only needs to implement the
+/// checks it cares about. Returning an error stops decoding and is surfaced as
+/// [`Status::Error`](crate::Status::Error).
+pub trait ValidationPolicy {
+    /// Called once the RSV bits of a frame have been read.
+    fn rsv(&mut self, rsv: [bool; 3]) -> Result<(), FrameError> {
+        let _ = rsv;
+        Ok(())
+    }
+
+    /// Called once the payload length has been read.
+    ///
+    /// `extended` is the size in bytes of the extended length field that
+    /// carried it: 0 for the 7-bit form, 2 for the 16-bit form and 8 for the
+    /// 64-bit form.
+    fn length(&mut self, payload_len: u64, extended: usize) -> Result<(), FrameError> {
+        let _ = (payload_len, extended);
+        Ok(())
+    }
+
+    /// Called when a control frame's head and payload length are known.
+    fn control(&mut self, head: &Head, payload_len: u64) -> Result<(), FrameError> {
+        let _ = (head, payload_len);
+        Ok(())
+    }
+
+    /// Called once the entire header has been decoded.
+    fn header(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        let _ = frame;
+        Ok(())
+    }
+}
+
+/// A policy that accepts every frame.
+///
+/// Passing it to [`Frame::decode_with`] opts out of the control frame checks
+/// made by [`Frame::decode`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AcceptAll;
+
+impl ValidationPolicy for AcceptAll {}
+
+/// A policy enforcing the RFC 6455 §5.5 rules for control frames: they must
+/// have FIN set and a payload of at most 125 bytes.
+///
+/// This is the policy used by [`Frame::decode`].
+///
+/// # Example
+/// ```
+/// use ws_frame::{AcceptAll, Frame, FrameError, Status};
+///
+/// // A Ping without FIN set.
+/// let buf = [0x09, 0x00];
+/// let mut f = Frame::empty();
+/// assert_eq!(Status::Error(FrameError::FragmentedControl), f.decode(&buf));
+/// assert!(f.decode_with(&buf, &mut AcceptAll).is_complete());
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ControlChecks;
+
+impl ValidationPolicy for ControlChecks {
+    fn control(&mut self, head: &Head, payload_len: u64) -> Result<(), FrameError> {
+        if !head.finished {
+            return Err(FrameError::FragmentedControl);
+        }
+        if payload_len > 125 {
+            return Err(FrameError::ControlFrameTooLong);
+        }
+        Ok(())
+    }
+}
+
+/// A policy enforcing every framing rule of RFC 6455 §5, for connections
+/// without extensions.
+///
+/// This is the policy used by [`Frame::decode_strict`].
+///
+/// # Example
+/// ```
+/// use ws_frame::{Frame, FrameError, Status};
+///
+/// let mut f = Frame::empty();
+/// assert_eq!(
+///     Status::Error(FrameError::ReservedBits),
+///     f.decode_strict(&[0xC1, 0x00])
+/// );
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Strict;
+
+impl ValidationPolicy for Strict {
+    fn rsv(&mut self, rsv: [bool; 3]) -> Result<(), FrameError> {
+        if rsv == [false; 3] {
+            Ok(())
+        } else {
+            Err(FrameError::ReservedBits)
+        }
+    }
+
+    fn length(&mut self, payload_len: u64, extended: usize) -> Result<(), FrameError> {
+        match extended {
+            2 if payload_len <= 125 => Err(FrameError::NonMinimalLength),
+            8 if payload_len <= 0xFFFF => Err(FrameError::NonMinimalLength),
+            _ => Ok(()),
+        }
+    }
+
+    fn control(&mut self, head: &Head, payload_len: u64) -> Result<(), FrameError> {
+        if let Opcode::Reserved(_) = head.op {
+            return Err(FrameError::InvalidOpcode);
+        }
+        ControlChecks.control(head, payload_len)
+    }
+
+    fn header(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        match frame.head {
+            Some(Head {
+                op: Opcode::Reserved(_),
+                ..
+            }) => Err(FrameError::InvalidOpcode),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::Status;
+
+    /// A policy rejecting every frame with a reserved bit set, and nothing
+    /// else.
+    pub(crate) struct NoRsv;
+
+    impl ValidationPolicy for NoRsv {
+        fn rsv(&mut self, rsv: [bool; 3]) -> Result<(), FrameError> {
+            if rsv == [false; 3] {
+                Ok(())
+            } else {
+                Err(FrameError::Rejected("rsv"))
+            }
+        }
+    }
+
+    #[test]
+    fn strict_is_conformant() {
+        assert!(crate::conformance::self_test(&mut Strict).is_conformant());
+    }
+
+    #[test]
+    fn rejects_before_header_is_complete() {
+        let mut f = Frame::empty();
+        assert_eq!(
+            Status::Error(FrameError::Rejected("rsv")),
+            f.decode_with(&[0b10100010], &mut NoRsv)
+        );
+        assert!(f.decode_with(&[0b10000010], &mut NoRsv).is_partial());
+    }
+}
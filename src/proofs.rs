@@ -0,0 +1,94 @@
+//! Kani proof harnesses for the header arithmetic.
+//!
+//! Run with `cargo kani`. Kani checks every harness for arithmetic overflow
+//! and out-of-bounds access on top of the assertions written here.
+
+use crate::{encode_uninit, Bytes, Frame, Head, Opcode, Status};
+use core::mem::MaybeUninit;
+
+/// The longest header, and so the longest prefix the decoder ever inspects.
+const MAX_HEAD_LEN: usize = 14;
+
+#[kani::proof]
+fn decode_accounts_for_every_header_byte() {
+    let buf: [u8; MAX_HEAD_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= MAX_HEAD_LEN);
+
+    let mut f = Frame::empty();
+    if let Status::Complete(consumed) = f.decode(&buf[..len]) {
+        let extended = match buf[1] & 0x7F {
+            126 => 2,
+            127 => 8,
+            _ => 0,
+        };
+        let mask = if f.mask.is_some() { 4 } else { 0 };
+        assert!(consumed <= len);
+        assert_eq!(consumed, 2 + extended + mask);
+
+        let payload_len = f.payload_len.unwrap();
+        match extended {
+            0 => assert!(payload_len <= 125),
+            2 => assert!(payload_len <= u64::from(u16::MAX)),
+            _ => {}
+        }
+    }
+}
+
+#[kani::proof]
+fn lint_reads_close_codes_in_bounds() {
+    let buf: [u8; MAX_HEAD_LEN + 2] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= buf.len());
+
+    let mut f = Frame::empty();
+    let _ = f.lint(&buf[..len], |_| {});
+}
+
+#[kani::proof]
+fn slice_to_stays_in_bounds() {
+    let buf: [u8; MAX_HEAD_LEN] = kani::any();
+    let skip: usize = kani::any();
+    let n: usize = kani::any();
+    kani::assume(skip <= MAX_HEAD_LEN);
+
+    let mut bytes = Bytes::new(&buf);
+    let _ = bytes.slice_to(skip);
+    let before = bytes.pos();
+    match bytes.slice_to(n) {
+        Some(slice) => {
+            assert_eq!(n, slice.len());
+            assert_eq!(before + n, bytes.pos());
+        }
+        None => assert_eq!(before, bytes.pos()),
+    }
+    assert!(bytes.pos() <= buf.len());
+}
+
+#[kani::proof]
+#[kani::unwind(9)]
+fn encoded_headers_decode_to_the_same_length() {
+    let payload: [u8; 4] = kani::any();
+    let len: usize = kani::any();
+    kani::assume(len <= payload.len());
+    let mask: Option<[u8; 4]> = kani::any();
+    let head = Head {
+        op: Opcode::Binary,
+        finished: kani::any(),
+        rsv: [false; 3],
+    };
+
+    let mut out = [MaybeUninit::uninit(); MAX_HEAD_LEN + 4];
+    let written = encode_uninit(&head, &payload[..len], mask, &mut out).unwrap();
+    let mut buf = [0; MAX_HEAD_LEN + 4];
+    for (b, out) in buf.iter_mut().zip(&out[..written]) {
+        // Safety: `encode_uninit` initialized the first `written` bytes.
+        *b = unsafe { out.assume_init() };
+    }
+
+    let mut f = Frame::empty();
+    let consumed = f.decode(&buf[..written]).unwrap();
+    assert_eq!(written, consumed + len);
+    assert_eq!(Some(len as u64), f.payload_len);
+    assert_eq!(mask, f.mask);
+}
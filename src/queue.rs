@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::io;
+
+/// What a bounded send queue does when a data frame doesn't fit.
+///
+/// Control frames are always queued, so Pongs and Closes still go out to a
+/// slow peer.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Overflow {
+    /// Drop the oldest queued data frames to make room.
+    DropOldest,
+    /// Fail the new send.
+    Reject,
+}
+
+/// Encoded frames waiting to be written.
+#[derive(Debug, Default)]
+pub(crate) struct SendQueue {
+    frames: VecDeque<(bool, Vec<u8>)>,
+    data_len: usize,
+    limit: Option<(usize, Overflow)>,
+}
+
+impl SendQueue {
+    /// Bounds the bytes of queued data frames to `limit`.
+    pub(crate) fn set_limit(&mut self, limit: usize, overflow: Overflow) {
+        self.limit = Some((limit, overflow));
+    }
+
+    /// Returns whether the queue is bounded.
+    pub(crate) fn is_bounded(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    /// Returns the amount of queued bytes.
+    pub(crate) fn len(&self) -> usize {
+        self.frames.iter().map(|(_, frame)| frame.len()).sum()
+    }
+
+    /// Returns whether no frames are queued.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Queues an encoded frame, applying the overflow policy to data frames.
+    pub(crate) fn push(&mut self, control: bool, frame: Vec<u8>) -> io::Result<()> {
+        if !control {
+            if let Some((limit, overflow)) = self.limit {
+                if frame.len() > limit {
+                    return Err(io::Error::other("frame is larger than the send queue"));
+                }
+                while self.data_len + frame.len() > limit {
+                    if overflow == Overflow::Reject {
+                        return Err(io::Error::other("send queue is full"));
+                    }
+                    let oldest = self.frames.iter().position(|&(control, _)| !control);
+                    let (_, dropped) = self.frames.remove(oldest.unwrap()).unwrap();
+                    self.data_len -= dropped.len();
+                }
+            }
+            self.data_len += frame.len();
+        }
+        self.frames.push_back((control, frame));
+        Ok(())
+    }
+
+    /// Queues an encoded Ping or Pong ahead of any queued data frames, which
+    /// RFC 6455 §5.4 allows between the fragments of a message.
+    pub(crate) fn push_urgent(&mut self, frame: Vec<u8>) {
+        let at = self
+            .frames
+            .iter()
+            .position(|&(control, _)| !control)
+            .unwrap_or(self.frames.len());
+        self.frames.insert(at, (true, frame));
+    }
+
+    /// Takes the oldest queued frame.
+    pub(crate) fn pop(&mut self) -> Option<Vec<u8>> {
+        let (control, frame) = self.frames.pop_front()?;
+        if !control {
+            self.data_len -= frame.len();
+        }
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_control_frames_on_overflow() {
+        let mut queue = SendQueue::default();
+        queue.set_limit(4, Overflow::DropOldest);
+        queue.push(false, vec![1; 3]).unwrap();
+        queue.push(true, vec![2; 8]).unwrap();
+        queue.push(false, vec![3; 3]).unwrap();
+        assert_eq!(Some(vec![2; 8]), queue.pop());
+        assert_eq!(Some(vec![3; 3]), queue.pop());
+        assert_eq!(None, queue.pop());
+
+        queue.set_limit(4, Overflow::Reject);
+        queue.push(false, vec![1; 3]).unwrap();
+        assert!(queue.push(false, vec![3; 3]).is_err());
+        assert!(queue.push(false, vec![3; 5]).is_err());
+    }
+
+    #[test]
+    fn urgent_frames_skip_data() {
+        let mut queue = SendQueue::default();
+        queue.push(true, vec![1]).unwrap();
+        queue.push(false, vec![2]).unwrap();
+        queue.push_urgent(vec![3]);
+        assert_eq!(Some(vec![1]), queue.pop());
+        assert_eq!(Some(vec![3]), queue.pop());
+        assert_eq!(Some(vec![2]), queue.pop());
+    }
+}
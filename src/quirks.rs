@@ -0,0 +1,26 @@
+/// Targeted leniencies for talking to peers known to break RFC 6455.
+///
+/// Each quirk relaxes a single check made by [`Connection`](crate::Connection)
+/// and [`WebSocketStream`](crate::WebSocketStream), so the rest of the
+/// validation stays in place. Everything is strict by default.
+///
+/// There is no quirk for bogus `permessage-deflate` window bits, since no
+/// extensions are negotiated.
+///
+/// # Example
+/// ```
+/// use ws_frame::Quirks;
+///
+/// let quirks = Quirks {
+///     unmasked_client_frames: true,
+///     ..Quirks::default()
+/// };
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Quirks {
+    /// Accept unmasked frames from clients, as some embedded clients send.
+    pub unmasked_client_frames: bool,
+    /// Accept a Close carrying 1005, which is reserved for local use and
+    /// must never be sent. The echo is sent without a code.
+    pub close_code_1005: bool,
+}
@@ -0,0 +1,64 @@
+use crate::{ControlChecks, Frame, FrameError, Head, ValidationPolicy};
+
+/// Which end of a connection is decoding.
+///
+/// Clients must mask every frame and servers must not, so each side rejects
+/// frames whose mask bit doesn't match. As a [`ValidationPolicy`], a `Role`
+/// also makes the checks of [`ControlChecks`].
+///
+/// # Example
+/// ```
+/// use ws_frame::{Frame, FrameError, Role, Status};
+///
+/// // An unmasked text frame, which only a server may send.
+/// let buf = [0x81, 0x00];
+/// let mut f = Frame::empty();
+/// assert!(f.decode_as(&buf, Role::Client).is_complete());
+/// assert_eq!(Status::Error(FrameError::MissingMask), f.decode_as(&buf, Role::Server));
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Role {
+    /// The server, decoding frames sent by a client.
+    Server,
+    /// The client, decoding frames sent by a server.
+    Client,
+}
+
+impl Role {
+    /// Returns whether frames received in this role must be masked.
+    pub fn expects_mask(self) -> bool {
+        self == Role::Server
+    }
+}
+
+impl ValidationPolicy for Role {
+    fn control(&mut self, head: &Head, payload_len: u64) -> Result<(), FrameError> {
+        ControlChecks.control(head, payload_len)
+    }
+
+    fn header(&mut self, frame: &Frame) -> Result<(), FrameError> {
+        match (self.expects_mask(), frame.mask.is_some()) {
+            (true, false) => Err(FrameError::MissingMask),
+            (false, true) => Err(FrameError::UnexpectedMask),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Status;
+
+    #[test]
+    fn clients_reject_masked_frames() {
+        let buf = [0x82, 0x80, 1, 2, 3, 4];
+        let mut f = Frame::empty();
+        assert_eq!(Status::Complete(6), f.decode_as(&buf, Role::Server));
+        assert_eq!(
+            Status::Error(FrameError::UnexpectedMask),
+            f.decode_as(&buf, Role::Client)
+        );
+        assert_eq!(Status::Partial(4), f.decode_as(&buf[..2], Role::Client));
+    }
+}
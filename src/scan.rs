@@ -0,0 +1,119 @@
+use core::cmp;
+
+use crate::{Frame, FrameError, Status};
+
+/// Decodes frame headers while skipping over their payloads.
+///
+/// A `Scanner` remembers how many payload bytes of the current frame are
+/// still to come, so payloads never need to be buffered or retained between
+/// reads. This suits monitoring taps that only care about headers.
+///
+/// # Example
+/// ```
+/// use ws_frame::Scanner;
+///
+/// let mut scanner = Scanner::new();
+/// let mut lens = Vec::new();
+///
+/// // A text frame with a 3 byte payload, split across two reads.
+/// let used = scanner.scan(&[0x81, 0x03, b'a'], |f| lens.push(f.payload_len)).unwrap();
+/// assert_eq!(3, used);
+/// assert_eq!(2, scanner.remaining());
+///
+/// let used = scanner.scan(&[b'b', b'c', 0x89, 0x00], |f| lens.push(f.payload_len)).unwrap();
+/// assert_eq!(4, used);
+/// assert_eq!(vec![Some(3), Some(0)], lens);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Scanner {
+    skip: u64,
+}
+
+impl Scanner {
+    /// Creates a new `Scanner` positioned at the start of a frame.
+    pub const fn new() -> Self {
+        Self { skip: 0 }
+    }
+
+    /// Returns the amount of payload bytes left to skip before the next
+    /// header.
+    pub fn remaining(&self) -> u64 {
+        self.skip
+    }
+
+    /// Scans `buf`, calling `on_frame` with each decoded header.
+    ///
+    /// Returns the amount of bytes consumed. Any bytes after that are the
+    /// start of an incomplete header, and must be passed again at the start of
+    /// the next call.
+    pub fn scan<F>(&mut self, buf: &[u8], mut on_frame: F) -> Result<usize, FrameError>
+    where
+        F: FnMut(&Frame),
+    {
+        self.scan_while(buf, |frame| {
+            on_frame(frame);
+            true
+        })
+        .map(|(pos, _)| pos)
+    }
+
+    /// Like `scan`, but stops right after a header for which `on_frame`
+    /// returns `false`. Also returns whether scanning stopped this way.
+    pub(crate) fn scan_while<F>(
+        &mut self,
+        buf: &[u8],
+        mut on_frame: F,
+    ) -> Result<(usize, bool), FrameError>
+    where
+        F: FnMut(&Frame) -> bool,
+    {
+        let mut pos = 0;
+
+        loop {
+            let skip = cmp::min(self.skip, (buf.len() - pos) as u64);
+            pos += skip as usize;
+            self.skip -= skip;
+
+            paranoid_assert!(pos <= buf.len());
+            paranoid_assert!(self.skip == 0 || pos == buf.len());
+            if self.skip > 0 || pos == buf.len() {
+                return Ok((pos, false));
+            }
+
+            let mut frame = Frame::empty();
+            match frame.decode(&buf[pos..]) {
+                Status::Complete(len) => {
+                    pos += len;
+                    self.skip = frame.payload_len.unwrap_or(0);
+                    if !on_frame(&frame) {
+                        return Ok((pos, true));
+                    }
+                }
+                Status::Partial(_) => return Ok((pos, false)),
+                Status::Error(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_split_across_reads() {
+        let mut scanner = Scanner::new();
+        let mut frames = 0;
+
+        assert_eq!(Ok(0), scanner.scan(&[0x82, 0x7E, 0x01], |_| frames += 1));
+        assert_eq!(
+            Ok(4),
+            scanner.scan(&[0x82, 0x7E, 0x01, 0x00], |_| frames += 1)
+        );
+        assert_eq!(1, frames);
+        assert_eq!(256, scanner.remaining());
+
+        assert_eq!(Ok(200), scanner.scan(&[0; 200], |_| frames += 1));
+        assert_eq!(56, scanner.remaining());
+    }
+}
@@ -0,0 +1,75 @@
+/// A value tagged with the sequence number of the frame it belongs to.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Sequenced<T> {
+    /// The frame's position in its direction of the connection, from 0.
+    pub seq: u64,
+    /// The tagged value, such as a frame, an event or an error.
+    pub value: T,
+}
+
+impl<T> Sequenced<T> {
+    /// Maps the value, keeping its sequence number.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Sequenced<U> {
+        Sequenced {
+            seq: self.seq,
+            value: f(self.value),
+        }
+    }
+}
+
+/// Assigns increasing sequence numbers to the frames going one way.
+///
+/// Keeping one `Sequencer` for decoded frames and another for encoded ones
+/// numbers each direction separately, so logs taken on both sides of a proxy
+/// can be lined up frame for frame. Errors can be tagged with the number of
+/// the frame that caused them.
+///
+/// [`Decoder::feed_sequenced`](crate::Decoder::feed_sequenced) and the
+/// `_sequenced` methods of the blocking writers keep one of these for their
+/// frames.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Scanner, Sequencer};
+///
+/// let mut seq = Sequencer::new();
+/// let mut frames = Vec::new();
+///
+/// let buf = [0x89, 0x00, 0x8A, 0x00];
+/// Scanner::new().scan(&buf, |f| frames.push(seq.tag(f.clone()))).unwrap();
+/// assert_eq!(1, frames[1].seq);
+///
+/// let err = seq.tag("decode failed");
+/// assert_eq!(2, err.seq);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Sequencer {
+    next: u64,
+}
+
+impl Sequencer {
+    /// Creates a `Sequencer` starting from 0.
+    pub const fn new() -> Self {
+        Self { next: 0 }
+    }
+
+    /// Returns the number the next frame will be given.
+    pub fn peek(&self) -> u64 {
+        self.next
+    }
+
+    /// Takes the next sequence number.
+    pub fn next_seq(&mut self) -> u64 {
+        let seq = self.next;
+        self.next = self.next.wrapping_add(1);
+        seq
+    }
+
+    /// Tags `value` with the next sequence number.
+    pub fn tag<T>(&mut self, value: T) -> Sequenced<T> {
+        Sequenced {
+            seq: self.next_seq(),
+            value,
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use std::io;
+
+use crate::blocking::invalid_data;
+use crate::encode::{encode_control_into, encode_to_vec};
+use crate::{
+    apply_mask, encode_close, encode_close_reply, parse_close, truncate_reason, CloseCode, Frame,
+    FrameError, Head, MaskKeyGenerator, Opcode, OsMaskKeys, Quirks, Utf8Validator,
+};
+
+/// The default largest message a connection accepts.
+const DEFAULT_MAX_MESSAGE: usize = 64 * 1024 * 1024;
+
+/// The protocol state of a connection, shared by the blocking and async
+/// connection types.
+#[derive(Debug)]
+pub(crate) struct Session {
+    client: bool,
+    keys: OsMaskKeys,
+    pub(crate) max_message: usize,
+    pub(crate) quirks: Quirks,
+    close_sent: bool,
+    message: Option<(Opcode, Vec<u8>)>,
+    utf8: Utf8Validator,
+}
+
+impl Session {
+    pub(crate) fn new(client: bool) -> Self {
+        Self {
+            client,
+            keys: OsMaskKeys,
+            max_message: DEFAULT_MAX_MESSAGE,
+            quirks: Quirks::default(),
+            close_sent: false,
+            message: None,
+            utf8: Utf8Validator::new(),
+        }
+    }
+
+    /// Validates a received header, returning the length of its payload.
+    pub(crate) fn payload_len(&self, frame: &Frame) -> io::Result<usize> {
+        let masked = frame.mask.is_some();
+        if masked == self.client && (masked || !self.quirks.unmasked_client_frames) {
+            return Err(protocol_error("frame masking doesn't match the role"));
+        }
+
+        // Only a continuation adds to the message in progress. Control frames
+        // between its fragments are capped at 125 bytes by the decoder.
+        let continues = frame.head.as_ref().map(|head| head.op) == Some(Opcode::Continue);
+        let buffered = match &self.message {
+            Some((_, payload)) if continues => payload.len(),
+            _ => 0,
+        };
+        frame
+            .payload_size()
+            .and_then(Result::ok)
+            .filter(|&len| len <= self.max_message.saturating_sub(buffered))
+            .ok_or_else(|| protocol_error("message is too long"))
+    }
+
+    /// Appends a frame holding `payload` to `out`, masked if this is a client.
+    ///
+    /// Sending a Close marks the closing handshake as started.
+    pub(crate) fn encode(
+        &mut self,
+        op: Opcode,
+        payload: &[u8],
+        out: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        let head = Head {
+            op,
+            finished: true,
+            rsv: [false; 3],
+        };
+        self.encode_frame(&head, payload, out)
+    }
+
+    /// Appends a frame with the header `head` to `out`, as with
+    /// [`encode`](Session::encode), such as one fragment of a message.
+    pub(crate) fn encode_frame(
+        &mut self,
+        head: &Head,
+        payload: &[u8],
+        out: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        let mask = self.next_mask();
+        encode_to_vec(head, payload, mask, out).map_err(invalid_data)?;
+        if head.op == Opcode::Close {
+            self.close_sent = true;
+        }
+        Ok(())
+    }
+
+    /// Appends a Close frame to `out`, starting the closing handshake.
+    pub(crate) fn close(&mut self, code: u16, reason: &str, out: &mut Vec<u8>) -> io::Result<()> {
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(truncate_reason(reason).as_bytes());
+        self.encode(Opcode::Close, &payload, out)
+    }
+
+    /// Appends a Close with the close code for `e` to `out`, unless one was
+    /// already sent, and returns `e` as an error to fail the connection with.
+    pub(crate) fn fail(&mut self, e: FrameError, out: &mut Vec<u8>) -> io::Error {
+        if !self.close_sent {
+            // Only a reason too long to send can fail, and this one is empty.
+            let _ = self.close(e.close_code(), "", out);
+        }
+        invalid_data(e)
+    }
+
+    /// Handles a received frame with its unmasked payload.
+    ///
+    /// Any reply is appended to `out`. Returns a message once one is
+    /// complete: Text, Binary, or the peer's Close.
+    pub(crate) fn receive(
+        &mut self,
+        head: &Head,
+        payload: Vec<u8>,
+        out: &mut Vec<u8>,
+    ) -> io::Result<Option<(Opcode, Vec<u8>)>> {
+        match head.op {
+            Opcode::Ping => self.encode(Opcode::Pong, &payload, out)?,
+            Opcode::Pong => {}
+            Opcode::Close => {
+                // The quirk lets 1005 through as if the Close had no status.
+                let lenient = self.quirks.close_code_1005 && payload.starts_with(&[0x03, 0xED]);
+                let checked = if lenient {
+                    core::str::from_utf8(&payload[2..])
+                        .map(drop)
+                        .map_err(|_| FrameError::InvalidUtf8)
+                } else {
+                    parse_close(&payload).map(drop)
+                };
+                if !self.close_sent {
+                    let mask = self.next_mask();
+                    let mut reply = [0; 8];
+                    let len = match checked {
+                        Ok(()) if lenient => {
+                            encode_control_into(Opcode::Close, &[], mask, &mut reply)
+                        }
+                        Err(_) if lenient => {
+                            encode_close(CloseCode::InvalidPayload.into(), "", mask, &mut reply)
+                        }
+                        _ => encode_close_reply(&payload, mask, &mut reply),
+                    };
+                    out.extend_from_slice(&reply[..len.map_err(invalid_data)?]);
+                    self.close_sent = true;
+                }
+                checked.map_err(invalid_data)?;
+                return Ok(Some((Opcode::Close, payload)));
+            }
+            Opcode::Text | Opcode::Binary if self.message.is_none() => {
+                if head.op == Opcode::Text {
+                    self.utf8.reset();
+                    self.check_text(&payload, head.finished)?;
+                }
+                if head.finished {
+                    return Ok(Some((head.op, payload)));
+                }
+                self.message = Some((head.op, payload));
+            }
+            Opcode::Continue => {
+                let text = match &self.message {
+                    Some((op, _)) => *op == Opcode::Text,
+                    None => return Err(protocol_error("unexpected continuation frame")),
+                };
+                if text {
+                    self.check_text(&payload, head.finished)?;
+                }
+                if let Some((_, buf)) = self.message.as_mut() {
+                    buf.extend_from_slice(&payload);
+                }
+                if head.finished {
+                    return Ok(self.message.take());
+                }
+            }
+            Opcode::Text | Opcode::Binary => {
+                return Err(protocol_error("new message started mid-message"))
+            }
+            Opcode::Reserved(_) => return Err(protocol_error("reserved opcode")),
+        }
+        Ok(None)
+    }
+
+    /// Returns the key to mask the next sent frame with, if this is a client.
+    fn next_mask(&mut self) -> Option<[u8; 4]> {
+        if self.client {
+            Some(self.keys.next_key())
+        } else {
+            None
+        }
+    }
+
+    /// Validates the next fragment of a text message as it arrives.
+    fn check_text(&mut self, payload: &[u8], finished: bool) -> io::Result<()> {
+        self.utf8
+            .feed(payload)
+            .and_then(|_| if finished { self.utf8.finish() } else { Ok(()) })
+            .map_err(|_| protocol_error("invalid UTF-8 in text message"))
+    }
+}
+
+pub(crate) fn unmask(mask: Option<[u8; 4]>, payload: &mut [u8]) {
+    if let Some(mask) = mask {
+        apply_mask(mask, payload);
+    }
+}
+
+fn protocol_error(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PayloadLen;
+
+    #[test]
+    fn quirks_relax_single_checks() {
+        let unmasked = Frame {
+            head: None,
+            mask: None,
+            payload_len: Some(0),
+            wire_len: Some(PayloadLen::Short(0)),
+        };
+        let close = Head {
+            op: Opcode::Close,
+            finished: true,
+            rsv: [false; 3],
+        };
+
+        let mut session = Session::new(false);
+        assert!(session.payload_len(&unmasked).is_err());
+        let mut out = Vec::new();
+        assert!(session.receive(&close, vec![0x03, 0xED], &mut out).is_err());
+        assert_eq!(&[0x88, 0x02, 0x03, 0xEA][..], &out[..]);
+
+        // Failing sent a Close, so start over to see the echo.
+        let mut session = Session::new(false);
+        session.quirks = Quirks {
+            unmasked_client_frames: true,
+            close_code_1005: true,
+        };
+        assert_eq!(0, session.payload_len(&unmasked).unwrap());
+        let mut out = Vec::new();
+        let message = session.receive(&close, vec![0x03, 0xED], &mut out).unwrap();
+        assert_eq!(Some((Opcode::Close, vec![0x03, 0xED])), message);
+        assert_eq!(&[0x88, 0x00][..], &out[..]);
+    }
+
+    #[test]
+    fn limits_only_continuations() {
+        let frame = |op, len| Frame {
+            head: Some(Head {
+                op,
+                finished: true,
+                rsv: [false; 3],
+            }),
+            mask: Some([0; 4]),
+            payload_len: Some(len),
+            wire_len: Some(PayloadLen::Short(len as u8)),
+        };
+        let mut session = Session::new(false);
+        session.max_message = 10;
+        session.message = Some((Opcode::Binary, vec![0; 8]));
+        assert_eq!(5, session.payload_len(&frame(Opcode::Ping, 5)).unwrap());
+        assert_eq!(2, session.payload_len(&frame(Opcode::Continue, 2)).unwrap());
+        assert!(session.payload_len(&frame(Opcode::Continue, 3)).is_err());
+    }
+
+    #[test]
+    fn answers_bad_close_reasons() {
+        let close = Head {
+            op: Opcode::Close,
+            finished: true,
+            rsv: [false; 3],
+        };
+        let mut session = Session::new(false);
+        let mut out = Vec::new();
+        assert!(session
+            .receive(&close, vec![0x03, 0xE8, 0xFF], &mut out)
+            .is_err());
+        assert_eq!(&[0x88, 0x02, 0x03, 0xEF][..], &out[..]);
+    }
+
+    #[test]
+    fn validates_text_across_fragments() {
+        let head = |op, finished| Head {
+            op,
+            finished,
+            rsv: [false; 3],
+        };
+        let mut session = Session::new(false);
+        let mut out = Vec::new();
+
+        let first = session.receive(&head(Opcode::Text, false), vec![0xE2, 0x82], &mut out);
+        assert_eq!(None, first.unwrap());
+        let last = session.receive(&head(Opcode::Continue, true), vec![0xAC], &mut out);
+        assert_eq!(Some((Opcode::Text, "€".into())), last.unwrap());
+
+        assert!(session
+            .receive(&head(Opcode::Text, false), vec![0xE2], &mut out)
+            .is_ok());
+        assert!(session
+            .receive(&head(Opcode::Continue, true), vec![b'a'], &mut out)
+            .is_err());
+    }
+}
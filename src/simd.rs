@@ -0,0 +1,102 @@
+//! Vectorized masking, used by [`apply_mask`](crate::apply_mask) with the
+//! `simd` feature.
+
+/// Masks the longest prefix of `payload` made of whole vectors, returning
+/// its length.
+///
+/// The length is always a multiple of 4, so the rest of the payload is masked
+/// with the same key.
+pub(crate) fn mask_prefix(key: [u8; 4], payload: &mut [u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if avx2_available() {
+            // SAFETY: AVX2 support was just checked.
+            return unsafe { x86::mask_avx2(key, payload) };
+        }
+        // SAFETY: SSE2 is part of every x86_64 target.
+        unsafe { x86::mask_sse2(key, payload) }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is part of every aarch64 target.
+        unsafe { neon::mask(key, payload) }
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let _ = (key, payload);
+        0
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
+fn avx2_available() -> bool {
+    std::is_x86_feature_detected!("avx2")
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+fn avx2_available() -> bool {
+    cfg!(target_feature = "avx2")
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn mask_avx2(key: [u8; 4], payload: &mut [u8]) -> usize {
+        let key = _mm256_set1_epi32(i32::from_ne_bytes(key));
+        let len = payload.len() / 32 * 32;
+        for chunk in payload[..len].chunks_exact_mut(32) {
+            let ptr = chunk.as_mut_ptr() as *mut __m256i;
+            _mm256_storeu_si256(ptr, _mm256_xor_si256(_mm256_loadu_si256(ptr), key));
+        }
+        len
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn mask_sse2(key: [u8; 4], payload: &mut [u8]) -> usize {
+        let key = _mm_set1_epi32(i32::from_ne_bytes(key));
+        let len = payload.len() / 16 * 16;
+        for chunk in payload[..len].chunks_exact_mut(16) {
+            let ptr = chunk.as_mut_ptr() as *mut __m128i;
+            _mm_storeu_si128(ptr, _mm_xor_si128(_mm_loadu_si128(ptr), key));
+        }
+        len
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use core::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn mask(key: [u8; 4], payload: &mut [u8]) -> usize {
+        let key = vreinterpretq_u8_u32(vdupq_n_u32(u32::from_ne_bytes(key)));
+        let len = payload.len() / 16 * 16;
+        for chunk in payload[..len].chunks_exact_mut(16) {
+            let ptr = chunk.as_mut_ptr();
+            vst1q_u8(ptr, veorq_u8(vld1q_u8(ptr), key));
+        }
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_bytewise_masking() {
+        let key = [0x12, 0x34, 0x56, 0x78];
+        let mut buf = [0; 100];
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let len = mask_prefix(key, &mut buf[3..]);
+        assert_eq!(0, len % 4);
+        for (i, &b) in buf[3..3 + len].iter().enumerate() {
+            assert_eq!((i + 3) as u8 ^ key[i % 4], b);
+        }
+        assert_eq!(3 + len, buf[3 + len] as usize);
+    }
+}
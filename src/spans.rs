@@ -0,0 +1,82 @@
+use core::ops::Range;
+
+use crate::{head_len, Frame, Status};
+
+/// Where each field of a decoded header lies in the buffer, as returned by
+/// [`Frame::decode_with_spans`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Spans {
+    /// The length field, including the 7-bit length in the second byte.
+    pub length: Range<usize>,
+    /// The mask key, if the frame is masked.
+    pub mask: Option<Range<usize>>,
+    /// The payload, which may extend past the end of the buffer.
+    pub payload: Range<usize>,
+}
+
+impl Frame {
+    /// Decode a buffer of bytes into this `Frame` as with
+    /// [`decode`](Frame::decode), also returning the byte range of each field
+    /// once the header is complete.
+    ///
+    /// The fin bit, RSV bits and opcode always make up the first byte.
+    ///
+    /// # Example
+    /// ```
+    /// use ws_frame::{Frame, Spans};
+    ///
+    /// let buf = [0x81, 0xFE, 0x00, 0x80, 1, 2, 3, 4];
+    /// let mut f = Frame::empty();
+    /// let (status, spans) = f.decode_with_spans(&buf);
+    ///
+    /// assert!(status.is_complete());
+    /// assert_eq!(
+    ///     Some(Spans { length: 1..4, mask: Some(4..8), payload: 8..136 }),
+    ///     spans
+    /// );
+    /// ```
+    pub fn decode_with_spans(&mut self, buf: &[u8]) -> (Status, Option<Spans>) {
+        let status = self.decode(buf);
+        let spans = match (&status, buf.get(1)) {
+            (Status::Complete(_), Some(&second)) => {
+                let head_len = head_len(second);
+                let mask_len = if self.mask.is_some() { 4 } else { 0 };
+                let length_end = head_len - mask_len;
+                let payload_end = self
+                    .payload_len_usize()
+                    .and_then(|len| head_len.checked_add(len))
+                    .unwrap_or(usize::MAX);
+
+                Some(Spans {
+                    length: 1..length_end,
+                    mask: self.mask.map(|_| length_end..head_len),
+                    payload: head_len..payload_end,
+                })
+            }
+            _ => None,
+        };
+        (status, spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_of_unmasked_frames() {
+        let buf = [0x82, 0x7F, 0, 0, 0, 0, 0, 1, 0, 0];
+        let mut f = Frame::empty();
+        let (_, spans) = f.decode_with_spans(&buf);
+        assert_eq!(
+            Some(Spans {
+                length: 1..10,
+                mask: None,
+                payload: 10..0x1000A,
+            }),
+            spans
+        );
+
+        assert_eq!(None, f.decode_with_spans(&buf[..9]).1);
+    }
+}
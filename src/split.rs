@@ -0,0 +1,190 @@
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Wake;
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{Opcode, WebSocketStream};
+
+/// The state both halves of a split stream share.
+#[derive(Debug)]
+struct Shared<S> {
+    stream: Mutex<WebSocketStream<S>>,
+    wakers: Arc<Wakers>,
+}
+
+/// The tasks polling each half.
+///
+/// Either half may end up waiting on either direction of the underlying
+/// stream, since the receive half writes replies to Pings and Closes, so the
+/// stream is polled with a waker that wakes both.
+#[derive(Debug, Default)]
+struct Wakers {
+    recv: Mutex<Option<Waker>>,
+    send: Mutex<Option<Waker>>,
+}
+
+impl Wake for Wakers {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        for waker in [&self.recv, &self.send] {
+            if let Some(waker) = waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<S> Shared<S> {
+    /// Registers `cx` for one half and polls the stream on behalf of both.
+    fn poll<T>(
+        &self,
+        slot: &Mutex<Option<Waker>>,
+        cx: &mut Context<'_>,
+        f: impl FnOnce(Pin<&mut WebSocketStream<S>>, &mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T>
+    where
+        S: Unpin,
+    {
+        *slot.lock().unwrap() = Some(cx.waker().clone());
+        let waker = Waker::from(self.wakers.clone());
+        let mut stream = self.lock();
+        f(Pin::new(&mut *stream), &mut Context::from_waker(&waker))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WebSocketStream<S>> {
+        self.stream.lock().unwrap()
+    }
+}
+
+/// The receiving half of a [`WebSocketStream`], created by
+/// [`split`](WebSocketStream::split).
+///
+/// This is a `Stream` of messages, and still answers Pings and Closes.
+#[derive(Debug)]
+pub struct RecvHalf<S> {
+    shared: Arc<Shared<S>>,
+}
+
+/// The sending half of a [`WebSocketStream`], created by
+/// [`split`](WebSocketStream::split).
+///
+/// This is a `Sink` of messages.
+#[derive(Debug)]
+pub struct SendHalf<S> {
+    shared: Arc<Shared<S>>,
+}
+
+pub(crate) fn split<S>(stream: WebSocketStream<S>) -> (SendHalf<S>, RecvHalf<S>) {
+    let shared = Arc::new(Shared {
+        stream: Mutex::new(stream),
+        wakers: Arc::default(),
+    });
+    (
+        SendHalf {
+            shared: shared.clone(),
+        },
+        RecvHalf { shared },
+    )
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SendHalf<S> {
+    /// Starts the closing handshake by queueing a Close frame, as with
+    /// [`WebSocketStream::close_with`].
+    pub fn close_with(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        self.shared.lock().close_with(code, reason)
+    }
+
+    /// Joins the halves back into a `WebSocketStream`.
+    ///
+    /// Returns the halves unchanged if they came from different streams.
+    pub fn reunite(self, recv: RecvHalf<S>) -> Result<WebSocketStream<S>, (Self, RecvHalf<S>)> {
+        if !Arc::ptr_eq(&self.shared, &recv.shared) {
+            return Err((self, recv));
+        }
+        drop(recv);
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => Ok(shared.stream.into_inner().unwrap()),
+            Err(_) => unreachable!("both halves were passed in"),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Stream for RecvHalf<S> {
+    type Item = io::Result<(Opcode, Vec<u8>)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let shared = &self.shared;
+        shared.poll(&shared.wakers.recv, cx, |stream, cx| stream.poll_next(cx))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Sink<(Opcode, Vec<u8>)> for SendHalf<S> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let shared = &self.shared;
+        shared.poll(&shared.wakers.send, cx, |stream, cx| stream.poll_ready(cx))
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: (Opcode, Vec<u8>)) -> io::Result<()> {
+        Pin::new(&mut *self.shared.lock()).start_send(message)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let shared = &self.shared;
+        shared.poll(&shared.wakers.send, cx, |stream, cx| stream.poll_flush(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let shared = &self.shared;
+        shared.poll(&shared.wakers.send, cx, |stream, cx| stream.poll_close(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+
+    #[tokio::test]
+    async fn halves_on_separate_tasks() {
+        let (client, server) = tokio::io::duplex(64);
+
+        let server = tokio::spawn(async move {
+            let ws = WebSocketStream::accept(server).await.unwrap();
+            let (mut send, mut recv) = ws.split();
+            for i in 0..3u8 {
+                send.send((Opcode::Binary, vec![i; 100])).await.unwrap();
+            }
+            let (op, _) = recv.next().await.unwrap().unwrap();
+            assert_eq!(Opcode::Close, op);
+            send.close().await.unwrap();
+        });
+
+        let ws = WebSocketStream::client(client, "localhost", "/")
+            .await
+            .unwrap();
+        let (mut send, mut recv) = ws.split();
+        let reader = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(Ok((op, payload))) = recv.next().await {
+                received.push((op, payload[0]));
+            }
+            received
+        });
+
+        send.close_with(1000, "").unwrap();
+        send.flush().await.unwrap();
+        let received = reader.await.unwrap();
+        assert_eq!(Some(&(Opcode::Close, 0x03)), received.last());
+        server.await.unwrap();
+    }
+}
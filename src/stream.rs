@@ -0,0 +1,192 @@
+use core::cmp;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+use futures_core::{ready, Stream};
+use futures_io::{AsyncBufRead, AsyncRead};
+
+use crate::blocking::invalid_data;
+use crate::{Frame, Status};
+
+/// An async source of frames parsed straight out of an `AsyncBufRead`.
+///
+/// Headers are decoded from the reader's own buffer and only the bytes they
+/// used are consumed, so no second buffer is needed. The `Stream` yields each
+/// header, and the payload of the latest one can then be read through
+/// `AsyncRead`. Any payload left unread is skipped before the next header.
+///
+/// # Example
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::{AsyncReadExt, StreamExt};
+/// use ws_frame::FrameStream;
+///
+/// let mut frames = FrameStream::new(&[0x81, 0x02, b'h', b'i', 0x89, 0x00][..]);
+///
+/// let text = frames.next().await.unwrap().unwrap();
+/// let mut payload = Vec::new();
+/// frames.read_to_end(&mut payload).await.unwrap();
+/// assert_eq!(Some(2), text.payload_len);
+/// assert_eq!(b"hi", &payload[..]);
+///
+/// assert!(frames.next().await.unwrap().is_ok());
+/// assert!(frames.next().await.is_none());
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct FrameStream<R> {
+    reader: R,
+    remaining: u64,
+    head: [u8; 14],
+    partial: usize,
+}
+
+impl<R> FrameStream<R> {
+    /// Creates a `FrameStream` reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            remaining: 0,
+            head: [0; 14],
+            partial: 0,
+        }
+    }
+
+    /// Returns the amount of payload bytes of the latest frame left to read.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Consumes this `FrameStream`, returning the underlying reader.
+    ///
+    /// Bytes of a partially read header are lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> FrameStream<R> {
+    fn poll_skip(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.remaining > 0 {
+            let buf = ready!(Pin::new(&mut self.reader).poll_fill_buf(cx))?;
+            if buf.is_empty() {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            let n = cmp::min(self.remaining, buf.len() as u64);
+            Pin::new(&mut self.reader).consume(n as usize);
+            self.remaining -= n;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for FrameStream<R> {
+    type Item = io::Result<Frame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Err(e) = ready!(this.poll_skip(cx)) {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        loop {
+            let buf = match ready!(Pin::new(&mut this.reader).poll_fill_buf(cx)) {
+                Ok(buf) => buf,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+            if buf.is_empty() {
+                return Poll::Ready(if this.partial == 0 {
+                    None
+                } else {
+                    Some(Err(io::ErrorKind::UnexpectedEof.into()))
+                });
+            }
+
+            // Headers split across reads are gathered in `head`, so they can
+            // be completed even if the reader only refills an empty buffer.
+            let mut frame = Frame::empty();
+            let partial = this.partial;
+            let n = cmp::min(this.head.len() - partial, buf.len());
+            let status = if partial == 0 {
+                frame.decode(buf)
+            } else {
+                this.head[partial..partial + n].copy_from_slice(&buf[..n]);
+                frame.decode(&this.head[..partial + n])
+            };
+
+            paranoid_assert!(partial < this.head.len());
+            match status {
+                Status::Complete(len) => {
+                    paranoid_assert!(len > partial && len - partial <= n);
+                    Pin::new(&mut this.reader).consume(len - partial);
+                    this.partial = 0;
+                    this.remaining = frame.payload_len.unwrap_or(0);
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                Status::Partial(_) => {
+                    if partial == 0 {
+                        this.head[..n].copy_from_slice(&buf[..n]);
+                    }
+                    Pin::new(&mut this.reader).consume(n);
+                    this.partial += n;
+                }
+                Status::Error(e) => return Poll::Ready(Some(Err(invalid_data(e)))),
+            }
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for FrameStream<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.remaining == 0 || out.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let buf = ready!(Pin::new(&mut this.reader).poll_fill_buf(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let n = cmp::min(cmp::min(out.len(), buf.len()) as u64, this.remaining) as usize;
+        paranoid_assert!(n > 0 && n <= buf.len());
+        out[..n].copy_from_slice(&buf[..n]);
+        Pin::new(&mut this.reader).consume(n);
+        this.remaining -= n as u64;
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::io::BufReader;
+    use futures::StreamExt;
+
+    #[test]
+    fn header_split_across_buffer_refills() {
+        let bytes = [0x82, 0x7E, 0x01, 0x00];
+        let payload = [0; 256];
+        let input: Vec<u8> = bytes.iter().chain(payload.iter()).copied().collect();
+
+        // A one byte buffer splits every header across refills.
+        let mut frames = FrameStream::new(BufReader::with_capacity(1, &input[..]));
+        let frame = block_on(frames.next()).unwrap().unwrap();
+        assert_eq!(Some(256), frame.payload_len);
+        assert!(block_on(frames.next()).is_none());
+
+        let mut frames = FrameStream::new(&[0x82, 0x7E][..]);
+        let err = block_on(frames.next()).unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+    }
+}
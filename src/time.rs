@@ -0,0 +1,116 @@
+use core::time::Duration;
+
+/// A reading from a monotonic clock.
+///
+/// This is implemented for `std::time::Instant`, and can be implemented over
+/// an embedded tick counter so timing features stay usable without `std`.
+///
+/// # Example
+/// ```
+/// use core::time::Duration;
+/// use ws_frame::{Instant, Millis};
+///
+/// let deadline = Millis(10).checked_add(Duration::from_millis(5)).unwrap();
+/// assert_eq!(Duration::from_millis(5), deadline.saturating_duration_since(Millis(10)));
+/// ```
+pub trait Instant: Copy + Ord {
+    /// Returns the time elapsed from `earlier` to `self`, or zero if
+    /// `earlier` is later than `self`.
+    fn saturating_duration_since(&self, earlier: Self) -> Duration;
+
+    /// Returns `self` moved `duration` forward, or `None` on overflow.
+    fn checked_add(&self, duration: Duration) -> Option<Self>;
+}
+
+/// A count of milliseconds from an arbitrary start, such as the ticks of a
+/// 1 kHz timer on a device without `std`.
+///
+/// # Example
+/// ```
+/// use core::sync::atomic::{AtomicU64, Ordering};
+/// use ws_frame::{Clock, Millis};
+///
+/// /// Advanced by a timer interrupt.
+/// static TICKS: AtomicU64 = AtomicU64::new(0);
+///
+/// struct Timer;
+///
+/// impl Clock for Timer {
+///     type Instant = Millis;
+///
+///     fn now(&self) -> Millis {
+///         Millis(TICKS.load(Ordering::Relaxed))
+///     }
+/// }
+///
+/// TICKS.store(250, Ordering::Relaxed);
+/// assert_eq!(Millis(250), Timer.now());
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Default)]
+pub struct Millis(pub u64);
+
+impl Instant for Millis {
+    fn saturating_duration_since(&self, earlier: Self) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0))
+    }
+
+    fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let ms = duration.as_millis();
+        if ms > u64::MAX as u128 {
+            return None;
+        }
+        self.0.checked_add(ms as u64).map(Millis)
+    }
+}
+
+/// A source of the current [`Instant`].
+pub trait Clock {
+    /// The instants produced by this clock.
+    type Instant: Instant;
+
+    /// Returns the current instant.
+    fn now(&self) -> Self::Instant;
+}
+
+#[cfg(feature = "std")]
+impl Instant for std::time::Instant {
+    fn saturating_duration_since(&self, earlier: Self) -> Duration {
+        std::time::Instant::saturating_duration_since(self, earlier)
+    }
+
+    fn checked_add(&self, duration: Duration) -> Option<Self> {
+        std::time::Instant::checked_add(self, duration)
+    }
+}
+
+/// A [`Clock`] reading `std::time::Instant::now`.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    type Instant = std::time::Instant;
+
+    fn now(&self) -> Self::Instant {
+        std::time::Instant::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn millis_saturate_and_overflow() {
+        assert_eq!(
+            Duration::ZERO,
+            Millis(5).saturating_duration_since(Millis(10))
+        );
+        assert_eq!(None, Millis(u64::MAX).checked_add(Duration::from_millis(1)));
+        assert_eq!(
+            Some(Millis(1_500)),
+            Millis(500).checked_add(Duration::from_secs(1))
+        );
+    }
+}
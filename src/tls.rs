@@ -0,0 +1,72 @@
+use std::convert::TryFrom;
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, ServerConfig, ServerConnection, StreamOwned};
+
+use crate::net::Url;
+use crate::{accept, client, Connection};
+
+/// A TLS client stream, as returned by [`connect_tls`].
+pub type TlsClientStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// A TLS server stream, as returned by [`accept_tls`].
+pub type TlsServerStream = StreamOwned<ServerConnection, TcpStream>;
+
+/// Connects to a `wss://` URL and performs the client handshake over TLS.
+///
+/// The URL's host is sent for SNI, and the server's certificate is checked by
+/// `config`'s verifier. ALPN isn't used for WebSockets over HTTP/1.1, so any
+/// protocols in `config` are dropped.
+///
+/// The crate doesn't pick a crypto provider; build `config` with whichever
+/// one the application uses.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+/// use rustls::ClientConfig;
+/// use ws_frame::connect_tls;
+///
+/// # fn config() -> ClientConfig { unimplemented!() }
+/// let config = Arc::new(config());
+/// let mut conn = connect_tls("wss://example.com/chat", config).unwrap();
+/// ```
+pub fn connect_tls(
+    url: &str,
+    config: Arc<ClientConfig>,
+) -> io::Result<Connection<TlsClientStream>> {
+    let url = Url::parse(url)?;
+    if !url.secure {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "expected a wss:// URL",
+        ));
+    }
+
+    let config = if config.alpn_protocols.is_empty() {
+        config
+    } else {
+        let mut config = ClientConfig::clone(&config);
+        config.alpn_protocols.clear();
+        Arc::new(config)
+    };
+    let name = ServerName::try_from(url.host.to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let tls = ClientConnection::new(config, name).map_err(io::Error::other)?;
+
+    let stream = TcpStream::connect((url.host, url.port))?;
+    client(StreamOwned::new(tls, stream), url.authority, &url.path)
+}
+
+/// Performs the TLS handshake and then the server handshake over an accepted
+/// stream.
+pub fn accept_tls(
+    stream: TcpStream,
+    config: Arc<ServerConfig>,
+) -> io::Result<Connection<TlsServerStream>> {
+    let tls = ServerConnection::new(config).map_err(io::Error::other)?;
+    accept(StreamOwned::new(tls, stream))
+}
@@ -0,0 +1,158 @@
+use core::cmp;
+use core::str;
+
+use crate::FrameError;
+
+/// Invalid UTF-8 found by a [`Utf8Validator`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Utf8Error {
+    /// The offset in the message of the sequence that isn't valid, counting
+    /// every byte fed so far.
+    pub offset: u64,
+}
+
+impl Utf8Error {
+    /// Returns the close code to fail the connection with: 1007, for data
+    /// inconsistent with the message type.
+    pub fn close_code(&self) -> u16 {
+        1007
+    }
+}
+
+impl From<Utf8Error> for FrameError {
+    fn from(_: Utf8Error) -> FrameError {
+        FrameError::InvalidUtf8
+    }
+}
+
+/// Validates the UTF-8 of a text message as its fragments arrive.
+///
+/// A character split across fragments is carried over to the next call to
+/// [`feed`](Utf8Validator::feed), so each fragment is checked as soon as it's
+/// received instead of once the whole message is buffered.
+///
+/// # Example
+/// ```
+/// use ws_frame::{Utf8Error, Utf8Validator};
+///
+/// let mut validator = Utf8Validator::new();
+/// // "€" split across two fragments.
+/// assert_eq!(Ok(()), validator.feed(&[b'a', 0xE2, 0x82]));
+/// assert_eq!(Ok(()), validator.feed(&[0xAC]));
+/// assert_eq!(Ok(()), validator.finish());
+///
+/// let mut validator = Utf8Validator::new();
+/// assert_eq!(Ok(()), validator.feed(&[b'a', 0xE2]));
+/// assert_eq!(Err(Utf8Error { offset: 1 }), validator.feed(&[b'b']));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Utf8Validator {
+    partial: [u8; 4],
+    partial_len: usize,
+    fed: u64,
+}
+
+impl Utf8Validator {
+    /// Creates a validator at the start of a message.
+    pub const fn new() -> Self {
+        Self {
+            partial: [0; 4],
+            partial_len: 0,
+            fed: 0,
+        }
+    }
+
+    /// Validates the next fragment of the message.
+    ///
+    /// Once an error is returned, the message is invalid and the validator
+    /// must be [`reset`](Utf8Validator::reset) before validating another.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), Utf8Error> {
+        let mut rest = chunk;
+
+        if self.partial_len > 0 {
+            let start = self.fed - self.partial_len as u64;
+            let take = cmp::min(4 - self.partial_len, rest.len());
+            let mut buf = self.partial;
+            buf[self.partial_len..self.partial_len + take].copy_from_slice(&rest[..take]);
+            let len = self.partial_len + take;
+
+            let used = match str::from_utf8(&buf[..len]) {
+                Ok(_) => take,
+                Err(e) if e.valid_up_to() > 0 => e.valid_up_to() - self.partial_len,
+                Err(e) if e.error_len().is_none() => {
+                    // Still incomplete, so every byte of the chunk was taken.
+                    self.partial = buf;
+                    self.partial_len = len;
+                    self.fed += take as u64;
+                    return Ok(());
+                }
+                Err(_) => return Err(Utf8Error { offset: start }),
+            };
+            self.partial_len = 0;
+            self.fed += used as u64;
+            rest = &rest[used..];
+        }
+
+        match str::from_utf8(rest) {
+            Ok(_) => {
+                self.fed += rest.len() as u64;
+                Ok(())
+            }
+            Err(e) if e.error_len().is_none() => {
+                let tail = &rest[e.valid_up_to()..];
+                self.partial[..tail.len()].copy_from_slice(tail);
+                self.partial_len = tail.len();
+                self.fed += rest.len() as u64;
+                Ok(())
+            }
+            Err(e) => Err(Utf8Error {
+                offset: self.fed + e.valid_up_to() as u64,
+            }),
+        }
+    }
+
+    /// Checks that the message didn't end partway through a character.
+    pub fn finish(&self) -> Result<(), Utf8Error> {
+        if self.partial_len > 0 {
+            Err(Utf8Error {
+                offset: self.fed - self.partial_len as u64,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the validator to the start of a message.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_split_agrees_with_std() {
+        let messages: [&[u8]; 4] = [
+            "κόσμε €𝄞".as_bytes(),
+            &[b'a', 0xF0, 0x9D, 0x84],
+            &[b'a', b'b', 0xED, 0xA0, 0x80],
+            &[0xE2, 0x82, 0xAC, 0xFF, b'a'],
+        ];
+        for message in &messages {
+            let expected = str::from_utf8(message).map_err(|e| e.valid_up_to() as u64);
+            for split in 0..=message.len() {
+                let (a, b) = message.split_at(split);
+                let mut validator = Utf8Validator::new();
+                let result = validator
+                    .feed(a)
+                    .and_then(|_| validator.feed(b))
+                    .and_then(|_| validator.finish())
+                    .map(|_| "")
+                    .map_err(|e| e.offset);
+                assert_eq!(expected.map(|_| ""), result, "split at {}", split);
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use core::cmp;
+use core::future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+use futures_core::ready;
+use futures_io::AsyncWrite;
+
+use crate::blocking::invalid_data;
+use crate::encode::encode_to_vec;
+use crate::{Head, MaskKeyGenerator, Opcode};
+
+/// The default largest payload sent in a single fragment.
+const DEFAULT_MAX_FRAGMENT: usize = 64 * 1024;
+
+/// Streams a single message of unknown length to an `AsyncWrite`.
+///
+/// Every `write` sends its bytes as a non-final fragment, and
+/// [`finish`](MessageWriter::finish) ends the message with an empty final
+/// fragment. This lets content be sent as it is generated, without buffering
+/// the whole message.
+///
+/// Fragments can be held back with [`cork`](MessageWriter::cork), so a burst
+/// of them is sent as a unit.
+///
+/// # Example
+/// ```
+/// # futures::executor::block_on(async {
+/// use futures::AsyncWriteExt;
+/// use ws_frame::{MessageWriter, Opcode};
+///
+/// let mut out = Vec::new();
+/// let mut writer = MessageWriter::new(&mut out, Opcode::Text);
+/// writer.write_all(b"hello").await.unwrap();
+/// writer.finish().await.unwrap();
+///
+/// assert_eq!(&[0x01, 0x05, b'h', b'e', b'l', b'l', b'o', 0x80, 0x00], &out[..]);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MessageWriter<W, K> {
+    writer: W,
+    keys: Option<K>,
+    op: Opcode,
+    max_fragment: usize,
+    pending: Vec<u8>,
+    written: usize,
+    finished: bool,
+    corked: bool,
+    cork_limit: Option<usize>,
+}
+
+impl<W> MessageWriter<W, fn() -> [u8; 4]> {
+    /// Creates a writer sending unmasked fragments, as a server does.
+    ///
+    /// # Panics
+    /// Panics if `op` isn't Text or Binary.
+    pub fn new(writer: W, op: Opcode) -> Self {
+        Self::with_keys(writer, op, None)
+    }
+}
+
+impl<W, K: MaskKeyGenerator> MessageWriter<W, K> {
+    /// Creates a writer masking every fragment with a key from `keys`, as a
+    /// client does.
+    ///
+    /// # Panics
+    /// Panics if `op` isn't Text or Binary.
+    pub fn masked(writer: W, op: Opcode, keys: K) -> Self {
+        Self::with_keys(writer, op, Some(keys))
+    }
+
+    fn with_keys(writer: W, op: Opcode, keys: Option<K>) -> Self {
+        assert!(
+            op == Opcode::Text || op == Opcode::Binary,
+            "messages must be Text or Binary"
+        );
+        Self {
+            writer,
+            keys,
+            op,
+            max_fragment: DEFAULT_MAX_FRAGMENT,
+            pending: Vec::new(),
+            written: 0,
+            finished: false,
+            corked: false,
+            cork_limit: None,
+        }
+    }
+
+    /// Holds back fragments until the writer is uncorked, flushed or
+    /// finished.
+    pub fn cork(&mut self) {
+        self.corked = true;
+    }
+
+    /// Stops holding back fragments. Held back fragments are sent by the next
+    /// call to write, flush or finish.
+    pub fn uncork(&mut self) {
+        self.corked = false;
+    }
+
+    /// Returns whether fragments are being held back.
+    pub fn is_corked(&self) -> bool {
+        self.corked
+    }
+
+    /// Sets the amount of held back bytes at which a corked writer sends them
+    /// anyway, or `None` to hold everything until uncorked.
+    pub fn set_cork_limit(&mut self, limit: Option<usize>) {
+        self.cork_limit = limit;
+    }
+
+    /// Sets the largest payload sent in a single fragment.
+    ///
+    /// A `write` with more bytes than this only sends a prefix of them.
+    ///
+    /// # Panics
+    /// Panics if `max` is zero.
+    pub fn max_fragment(mut self, max: usize) -> Self {
+        assert!(max > 0, "fragments must be allowed a payload");
+        self.max_fragment = max;
+        self
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes this `MessageWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn encode(&mut self, finished: bool, payload: &[u8]) -> io::Result<()> {
+        paranoid_assert!(!self.finished, "encoded a fragment after the final one");
+        let head = Head {
+            op: core::mem::replace(&mut self.op, Opcode::Continue),
+            finished,
+            rsv: [false; 3],
+        };
+        let mask = self.keys.as_mut().map(|keys| keys.next_key());
+        encode_to_vec(&head, payload, mask, &mut self.pending).map_err(invalid_data)
+    }
+}
+
+impl<W: AsyncWrite + Unpin, K: MaskKeyGenerator + Unpin> MessageWriter<W, K> {
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let n =
+                ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+            paranoid_assert!(self.written <= self.pending.len());
+        }
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Sends the final fragment and flushes the underlying writer.
+    ///
+    /// Further writes fail once the message is finished.
+    pub fn poll_finish(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_pending(cx))?;
+        if !self.finished {
+            self.encode(true, &[])?;
+            self.finished = true;
+            ready!(self.poll_pending(cx))?;
+        }
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    /// Sends the final fragment and flushes the underlying writer.
+    pub async fn finish(&mut self) -> io::Result<()> {
+        future::poll_fn(|cx| self.poll_finish(cx)).await
+    }
+}
+
+impl<W: AsyncWrite + Unpin, K: MaskKeyGenerator + Unpin> AsyncWrite for MessageWriter<W, K> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Err(io::Error::other("message already finished")));
+        }
+        let held = this.corked
+            && this
+                .cork_limit
+                .is_none_or(|limit| this.pending.len() < limit);
+        if !held {
+            ready!(this.poll_pending(cx))?;
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        // The fragment is buffered and sent by a later call to write, flush
+        // or finish.
+        let n = cmp::min(buf.len(), this.max_fragment);
+        this.encode(false, &buf[..n])?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_finish(cx))?;
+        Pin::new(&mut this.writer).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+    use futures::executor::block_on;
+    use futures::AsyncWriteExt;
+
+    #[test]
+    fn masked_fragments() {
+        let mut out = Vec::new();
+        let mut writer =
+            MessageWriter::masked(&mut out, Opcode::Binary, || [1, 2, 3, 4]).max_fragment(2);
+        block_on(async {
+            writer.write_all(&[0xFF; 3]).await.unwrap();
+            writer.close().await.unwrap();
+        });
+        assert!(block_on(writer.write(&[0])).is_err());
+
+        let mut f = Frame::empty();
+        assert!(f.decode(&out).is_complete());
+        assert_eq!(Opcode::Binary, f.head.as_ref().unwrap().op);
+        assert_eq!(Some([1, 2, 3, 4]), f.mask);
+        assert_eq!(&[0xFF ^ 1, 0xFF ^ 2], &out[6..8]);
+
+        // Two non-final fragments and the empty final one.
+        assert_eq!(8 + 7 + 6, out.len());
+        assert_eq!(0x80, out[15]);
+    }
+
+    #[test]
+    fn corked_fragments_are_sent_together() {
+        let mut out = Vec::new();
+        let mut writer = MessageWriter::new(&mut out, Opcode::Text);
+        writer.cork();
+        block_on(async {
+            writer.write_all(b"a").await.unwrap();
+            writer.write_all(b"b").await.unwrap();
+        });
+        assert!(writer.get_ref().is_empty());
+
+        block_on(writer.flush()).unwrap();
+        assert_eq!(&[0x01, 0x01, b'a', 0x00, 0x01, b'b'], &writer.get_ref()[..]);
+    }
+}
@@ -0,0 +1,625 @@
+use core::future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::blocking::invalid_data;
+use crate::handshake::{
+    find_head_end, parse_request, write_rejection, write_response, ClientHandshake, HandshakeError,
+    Request, MAX_HEAD_LEN,
+};
+use crate::queue::{Overflow, SendQueue};
+use crate::session::{unmask, Session};
+use crate::split::{self, RecvHalf, SendHalf};
+use crate::{
+    CloseCode, Extensions, Fragmenter, Frame, FrameError, Head, Opcode, OwnedMessage, Quirks, Role,
+    Status,
+};
+
+/// The amount of queued bytes at which `poll_ready` writes them out first.
+const WRITE_THRESHOLD: usize = 64 * 1024;
+
+/// An async WebSocket connection over a tokio stream.
+///
+/// After the handshake, this is a `Stream` of received messages and a `Sink`
+/// of messages to send, each an opcode and a payload. As with
+/// [`Connection`](crate::Connection), fragmented messages are reassembled,
+/// Pings are answered and Pongs are skipped, so only Text, Binary and Close
+/// messages are yielded. Replies are queued and written out opportunistically;
+/// flush or close the sink after a Close arrives to make sure the echo is sent.
+///
+/// Sent frames wait in a queue until written. By default `poll_ready` waits
+/// for the queue to drain below 64KiB, but a bounded queue set with
+/// [`set_send_queue`](WebSocketStream::set_send_queue) never waits on a slow
+/// peer, and applies its [`Overflow`] policy instead.
+///
+/// # Example
+/// ```no_run
+/// # async fn run() -> std::io::Result<()> {
+/// use futures::{SinkExt, StreamExt};
+/// use ws_frame::{Opcode, WebSocketStream};
+///
+/// # let (io, _) = tokio::io::duplex(64);
+/// let mut ws = WebSocketStream::client(io, "localhost:9001", "/").await?;
+///
+/// ws.send((Opcode::Text, b"hello".to_vec())).await?;
+/// while let Some(message) = ws.next().await {
+///     let (op, payload) = message?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WebSocketStream<S> {
+    io: S,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    queue: SendQueue,
+    session: Session,
+    extensions: Extensions,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> WebSocketStream<S> {
+    /// Performs the client handshake, requesting `path` on `host`.
+    pub async fn client(io: S, host: &str, path: &str) -> io::Result<Self> {
+        let handshake = ClientHandshake::new();
+        let mut request = Vec::new();
+        handshake.write_request(host, path, &mut request);
+
+        let mut ws = Self::new(io, true);
+        ws.io.write_all(&request).await?;
+        ws.io.flush().await?;
+
+        let len = ws.read_http_head().await?;
+        match handshake.parse_response(&ws.read_buf[..len]) {
+            Ok(Some(_)) => {
+                ws.read_buf.drain(..len);
+                Ok(ws)
+            }
+            Ok(None) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete handshake response",
+            )),
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    /// Performs the server handshake.
+    pub async fn accept(io: S) -> io::Result<Self> {
+        Self::accept_with(io, |_| Ok(None)).await
+    }
+
+    /// Performs the server handshake, letting `callback` inspect the request
+    /// first, as with [`accept_with`](crate::accept_with).
+    pub async fn accept_with<F>(io: S, callback: F) -> io::Result<Self>
+    where
+        F: FnOnce(&Request) -> Result<Option<String>, HandshakeError>,
+    {
+        let mut ws = Self::new(io, false);
+        let len = ws.read_http_head().await?;
+
+        let accepted = parse_request(&ws.read_buf[..len]).and_then(|request| {
+            // A head that ends early, such as a lone blank line, is malformed.
+            let (request, _) = request.ok_or(HandshakeError::Http(httparse::Error::Token))?;
+            let protocol = callback(&request)?;
+            Ok((request, protocol))
+        });
+        ws.read_buf.drain(..len);
+
+        let mut response = Vec::new();
+        match accepted {
+            Ok((request, protocol)) => {
+                write_response(&request.key, protocol.as_deref(), &mut response);
+                ws.io.write_all(&response).await?;
+                ws.io.flush().await?;
+                Ok(ws)
+            }
+            Err(e) => {
+                write_rejection(&e, &mut response);
+                ws.io.write_all(&response).await?;
+                Err(io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+
+    /// Wraps a stream whose handshake was already done elsewhere, speaking
+    /// as `role`.
+    ///
+    /// This is for transports handed over by a server framework after its own
+    /// 101 response, such as with
+    /// [`from_upgraded`](WebSocketStream::from_upgraded).
+    pub fn from_raw(io: S, role: Role) -> Self {
+        Self::new(io, role == Role::Client)
+    }
+
+    fn new(io: S, client: bool) -> Self {
+        Self {
+            io,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            queue: SendQueue::default(),
+            session: Session::new(client),
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// Sets the largest message the stream accepts.
+    pub fn set_max_message(&mut self, max: usize) {
+        self.session.max_message = max;
+    }
+
+    /// Sets the leniencies used when validating the peer's frames.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.session.quirks = quirks;
+    }
+
+    /// Bounds the bytes of data frames waiting to be written to `limit`,
+    /// applying `overflow` to sends that don't fit.
+    pub fn set_send_queue(&mut self, limit: usize, overflow: Overflow) {
+        self.queue.set_limit(limit, overflow);
+    }
+
+    /// Starts the closing handshake by queueing a Close frame.
+    ///
+    /// The reason is truncated to fit in the frame. Flush the sink to send it,
+    /// and keep polling the stream until the peer's Close arrives.
+    pub fn close_with(&mut self, code: u16, reason: &str) -> io::Result<()> {
+        let mut frame = Vec::new();
+        self.session.close(code, reason, &mut frame)?;
+        self.queue.push(true, frame)
+    }
+
+    /// Turns the stream into a [`Messages`] stream and sink, which send and
+    /// yield [`OwnedMessage`]s.
+    pub fn into_messages(self) -> Messages<S> {
+        Messages {
+            ws: self,
+            max_frame: usize::MAX,
+        }
+    }
+
+    /// Splits the stream into halves that can be used from separate tasks.
+    ///
+    /// The halves share the connection's state, so the receive half keeps
+    /// answering Pings and Closes while the send half is in use.
+    pub fn split(self) -> (SendHalf<S>, RecvHalf<S>) {
+        split::split(self)
+    }
+
+    /// Returns the state extensions and middleware keep on this connection.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns a mutable reference to this connection's extension state.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.io
+    }
+
+    /// Reads until `read_buf` starts with an HTTP message head, returning
+    /// its length.
+    async fn read_http_head(&mut self) -> io::Result<usize> {
+        let mut searched = 0;
+        loop {
+            if let Some(end) = find_head_end(&self.read_buf, searched) {
+                return Ok(end);
+            }
+            if self.read_buf.len() > MAX_HEAD_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "handshake head is too long",
+                ));
+            }
+
+            searched = self.read_buf.len();
+            if future::poll_fn(|cx| self.poll_fill(cx)).await? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Reads more bytes into `read_buf`, returning how many were read.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut chunk = [0; 8 * 1024];
+        let mut buf = ReadBuf::new(&mut chunk);
+        ready!(Pin::new(&mut self.io).poll_read(cx, &mut buf))?;
+        self.read_buf.extend_from_slice(buf.filled());
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+
+    /// Returns whether any frames are waiting to be written.
+    fn has_pending(&self) -> bool {
+        !self.write_buf.is_empty() || !self.queue.is_empty()
+    }
+
+    /// Writes out the queue and flushes.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if self.write_buf.is_empty() {
+                match self.queue.pop() {
+                    Some(frame) => self.write_buf = frame,
+                    None => break,
+                }
+            }
+            let n = ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_buf.drain(..n);
+        }
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    /// Takes the next complete frame out of `read_buf`.
+    fn next_frame(&mut self) -> io::Result<Option<(Head, Vec<u8>)>> {
+        let mut frame = Frame::empty();
+        let len = match frame.decode_strict(&self.read_buf) {
+            Status::Complete(len) => len,
+            Status::Partial(_) => return Ok(None),
+            Status::Error(e) => {
+                let mut reply = Vec::new();
+                let e = self.session.fail(e, &mut reply);
+                if !reply.is_empty() {
+                    self.queue.push(true, reply)?;
+                }
+                return Err(e);
+            }
+        };
+
+        let payload_len = self.session.payload_len(&frame)?;
+        if self.read_buf.len() - len < payload_len {
+            return Ok(None);
+        }
+        let mut payload = self.read_buf[len..len + payload_len].to_vec();
+        unmask(frame.mask, &mut payload);
+        self.read_buf.drain(..len + payload_len);
+
+        Ok(frame.head.map(|head| (head, payload)))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Stream for WebSocketStream<S> {
+    type Item = io::Result<(Opcode, Vec<u8>)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            // Replies to Pings and Closes are sent without waiting on them.
+            if this.has_pending() {
+                if let Poll::Ready(Err(e)) = this.poll_write_buf(cx) {
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+
+            match this.next_frame() {
+                Ok(Some((head, payload))) => {
+                    let mut reply = Vec::new();
+                    let received = this.session.receive(&head, payload, &mut reply);
+                    if !reply.is_empty() {
+                        this.queue.push(true, reply)?;
+                    }
+                    match received {
+                        Ok(Some(message)) => {
+                            if this.has_pending() {
+                                if let Poll::Ready(Err(e)) = this.poll_write_buf(cx) {
+                                    return Poll::Ready(Some(Err(e)));
+                                }
+                            }
+                            return Poll::Ready(Some(Ok(message)));
+                        }
+                        Ok(None) => {}
+                        Err(e) => return Poll::Ready(Some(Err(e))),
+                    }
+                }
+                Ok(None) => match ready!(this.poll_fill(cx)) {
+                    Ok(0) if this.read_buf.is_empty() => return Poll::Ready(None),
+                    Ok(0) => return Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into()))),
+                    Ok(_) => {}
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                },
+                Err(e) => {
+                    // Send the Close failing the connection if it goes out
+                    // without waiting. The connection is failing either way.
+                    let _ = this.poll_write_buf(cx);
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Sink<(Opcode, Vec<u8>)> for WebSocketStream<S> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.queue.is_bounded() {
+            if let Poll::Ready(Err(e)) = this.poll_write_buf(cx) {
+                return Poll::Ready(Err(e));
+            }
+        } else if this.write_buf.len() + this.queue.len() >= WRITE_THRESHOLD {
+            ready!(this.poll_write_buf(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, (op, payload): (Opcode, Vec<u8>)) -> io::Result<()> {
+        let this = self.get_mut();
+        let mut frame = Vec::new();
+        this.session.encode(op, &payload, &mut frame)?;
+        this.queue.push(op.is_control(), frame)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_write_buf(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.io).poll_shutdown(cx)
+    }
+}
+
+/// A [`WebSocketStream`] at message granularity, made with
+/// [`into_messages`](WebSocketStream::into_messages).
+///
+/// This is a `Stream` of received [`OwnedMessage`]s and a `Sink` of messages
+/// to send. Sent Text and Binary messages are split into frames of at most
+/// [`set_max_frame`](Messages::set_max_frame) bytes and masked if this is a
+/// client. Pings and Pongs go ahead of data frames still in the queue, while
+/// a Close stays behind the data sent before it.
+///
+/// # Example
+/// ```no_run
+/// # async fn run() -> std::io::Result<()> {
+/// use futures::{SinkExt, StreamExt};
+/// use ws_frame::{OwnedMessage, WebSocketStream};
+///
+/// # let (io, _) = tokio::io::duplex(64);
+/// let ws = WebSocketStream::client(io, "localhost:9001", "/").await?;
+/// let mut ws = ws.into_messages();
+///
+/// ws.send(OwnedMessage::Text("hello".into())).await?;
+/// while let Some(message) = ws.next().await {
+///     if let OwnedMessage::Text(text) = message? {}
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Messages<S> {
+    ws: WebSocketStream<S>,
+    max_frame: usize,
+}
+
+impl<S> Messages<S> {
+    /// Sets the largest payload of a frame sent for a Text or Binary message,
+    /// which is unlimited by default.
+    ///
+    /// # Panics
+    /// Panics if `max` is 0.
+    pub fn set_max_frame(&mut self, max: usize) {
+        assert!(max > 0, "frames must carry at least a byte");
+        self.max_frame = max;
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &WebSocketStream<S> {
+        &self.ws
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut WebSocketStream<S> {
+        &mut self.ws
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> WebSocketStream<S> {
+        self.ws
+    }
+
+    /// Queues `payload` as a message of `op`, split into fragments.
+    fn queue_data(&mut self, op: Opcode, payload: &[u8]) -> io::Result<()> {
+        let mut frames = Vec::new();
+        for (head, range) in Fragmenter::new(op, payload.len(), self.max_frame) {
+            self.ws
+                .session
+                .encode_frame(&head, &payload[range], &mut frames)?;
+        }
+        self.ws.queue.push(false, frames)
+    }
+
+    /// Queues a Ping or Pong ahead of any queued data.
+    fn queue_urgent(&mut self, op: Opcode, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > 125 {
+            return Err(invalid_data(FrameError::ControlFrameTooLong));
+        }
+        let mut frame = Vec::new();
+        self.ws.session.encode(op, payload, &mut frame)?;
+        self.ws.queue.push_urgent(frame);
+        Ok(())
+    }
+}
+
+/// Converts a message yielded by a [`WebSocketStream`].
+fn into_message(op: Opcode, payload: Vec<u8>) -> io::Result<OwnedMessage> {
+    match op {
+        Opcode::Text => String::from_utf8(payload)
+            .map(OwnedMessage::Text)
+            .map_err(|_| invalid_data(FrameError::InvalidUtf8)),
+        Opcode::Binary => Ok(OwnedMessage::Binary(payload)),
+        // The session already checked the code, which quirks may loosen.
+        _ => match payload.get(..2) {
+            Some(&[hi, lo]) => {
+                let reason = core::str::from_utf8(&payload[2..])
+                    .map_err(|_| invalid_data(FrameError::InvalidUtf8))?;
+                let code = CloseCode::from(u16::from_be_bytes([hi, lo]));
+                Ok(OwnedMessage::Close(Some((code, reason.into()))))
+            }
+            _ => Ok(OwnedMessage::Close(None)),
+        },
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Stream for Messages<S> {
+    type Item = io::Result<OwnedMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(Pin::new(&mut self.get_mut().ws).poll_next(cx));
+        Poll::Ready(item.map(|message| message.and_then(|(op, payload)| into_message(op, payload))))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Sink<OwnedMessage> for Messages<S> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Sink::<(Opcode, Vec<u8>)>::poll_ready(Pin::new(&mut self.get_mut().ws), cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: OwnedMessage) -> io::Result<()> {
+        let this = self.get_mut();
+        match message {
+            OwnedMessage::Text(text) => this.queue_data(Opcode::Text, text.as_bytes()),
+            OwnedMessage::Binary(payload) => this.queue_data(Opcode::Binary, &payload),
+            OwnedMessage::Ping(payload) => this.queue_urgent(Opcode::Ping, &payload),
+            OwnedMessage::Pong(payload) => this.queue_urgent(Opcode::Pong, &payload),
+            OwnedMessage::Close(Some((code, _))) if !code.is_allowed_on_wire() => {
+                Err(invalid_data(FrameError::InvalidCloseCode))
+            }
+            OwnedMessage::Close(Some((code, reason))) => this.ws.close_with(code.into(), &reason),
+            OwnedMessage::Close(None) => {
+                let mut frame = Vec::new();
+                this.ws.session.encode(Opcode::Close, &[], &mut frame)?;
+                this.ws.queue.push(true, frame)
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Sink::<(Opcode, Vec<u8>)>::poll_flush(Pin::new(&mut self.get_mut().ws), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Sink::<(Opcode, Vec<u8>)>::poll_close(Pin::new(&mut self.get_mut().ws), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+
+    #[tokio::test]
+    async fn echo_over_duplex() {
+        let (client, server) = tokio::io::duplex(64);
+
+        let server = tokio::spawn(async move {
+            let mut ws = WebSocketStream::accept(server).await.unwrap();
+            while let Some(message) = ws.next().await {
+                let (op, payload) = message.unwrap();
+                if op == Opcode::Close {
+                    ws.close().await.unwrap();
+                    break;
+                }
+                ws.send((op, payload)).await.unwrap();
+            }
+        });
+
+        let mut ws = WebSocketStream::client(client, "localhost", "/")
+            .await
+            .unwrap();
+        let payload = vec![7; 1000];
+        ws.send((Opcode::Binary, payload.clone())).await.unwrap();
+        assert_eq!((Opcode::Binary, payload), ws.next().await.unwrap().unwrap());
+
+        ws.close_with(1000, "").unwrap();
+        ws.flush().await.unwrap();
+        let (op, _) = ws.next().await.unwrap().unwrap();
+        assert_eq!(Opcode::Close, op);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wraps_raw_streams() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = WebSocketStream::from_raw(client, Role::Client);
+        let mut server = WebSocketStream::from_raw(server, Role::Server);
+
+        client.send((Opcode::Text, b"hi".to_vec())).await.unwrap();
+        assert_eq!(
+            (Opcode::Text, b"hi".to_vec()),
+            server.next().await.unwrap().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_messages_in_fragments() {
+        use tokio::io::AsyncReadExt;
+
+        let (io, mut peer) = tokio::io::duplex(1024);
+        let mut ws = WebSocketStream::from_raw(io, Role::Server).into_messages();
+        ws.set_max_frame(2);
+
+        ws.feed(OwnedMessage::Text("hello".into())).await.unwrap();
+        ws.feed(OwnedMessage::Close(Some((
+            CloseCode::Normal,
+            String::new(),
+        ))))
+        .await
+        .unwrap();
+        ws.feed(OwnedMessage::Ping(b"p".to_vec())).await.unwrap();
+        ws.flush().await.unwrap();
+
+        let mut sent = [0; 18];
+        peer.read_exact(&mut sent).await.unwrap();
+        assert_eq!(
+            [
+                0x89, 0x01, b'p', 0x01, 0x02, b'h', b'e', 0x00, 0x02, b'l', b'l', 0x80, 0x01, b'o',
+                0x88, 0x02, 0x03, 0xE8,
+            ],
+            sent
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_heads() {
+        use tokio::io::AsyncReadExt;
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let peer = tokio::spawn(async move {
+            let mut buf = [0; 1024];
+            let _ = server.read(&mut buf).await.unwrap();
+            server.write_all(b"\r\n\r\n").await.unwrap();
+        });
+        let e = WebSocketStream::client(client, "localhost", "/")
+            .await
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+        peer.await.unwrap();
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        client.write_all(b"\r\n\r\n").await.unwrap();
+        let e = WebSocketStream::accept(server).await.unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+    }
+}